@@ -0,0 +1,384 @@
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::Features;
+use vulkano::sync;
+use vulkano::sync::GpuFuture;
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::image::StorageImage;
+use vulkano::format::ClearValue;
+use image::{ImageBuffer, Rgba};
+
+use vulkano_tutorial::{VulkanInit, InstanceConfig, DeviceRequirements, Profiler, MandelbrotParams, MandelbrotPipeline,
+                       FractalKind, Palette, Precision, ReferenceOrbit, FixedPoint, MAX_MULTIBROT_POWER,
+                       create_instance};
+use vulkano_tutorial::frame_timer::duration_ms;
+
+const USAGE: &str = "Usage: mandelbrot [options]
+
+Options:
+    -w, --width <pixels>        Image width (default 1024)
+    -h, --height <pixels>       Image height (default 1024)
+    -f, --fractal <name>        One of: {fractals} (default mandelbrot)
+        --julia-c <re,im>       Constant of the Julia set (default -0.8,0.156)
+        --power <n>             Exponent of the Multibrot set, above 1 up to 64 (default 3)
+    -c, --center <re,im>        Point in the middle of the image (default depends on the fractal),
+                                perturbation keeps every digit
+    -z, --zoom <factor>         Magnification, 1 shows the whole set (default 1)
+        --precision <name>      One of: {precisions} (default: the fastest one
+                                that is precise enough for the zoom)
+    -i, --iterations <count>    Maximum iterations per pixel (default 256)
+    -p, --palette <name|file>   One of: {palettes} (default classic),
+                                or a file of RRGGBB hex stops
+        --palette-offset <t>    Shift the palette, 1 is a full cycle (default 0)
+        --palette-density <n>   Palette repeats over the iteration range (default 1)
+        --no-smooth             Color by whole iteration counts, showing bands
+    -o, --output <path>         Where to write the PNG (default image.png)
+        --help                  Print this message";
+
+/// Everything that can be set from the command line.
+struct Args {
+    width: u32,
+    height: u32,
+    params: MandelbrotParams,
+    /// The center as it was typed, for perturbation.
+    center_text: [String; 2],
+    precision: Option<Precision>,
+    palette: Palette,
+    output: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", usage());
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, usage());
+            process::exit(2);
+        }
+    };
+    let (width, height) = (args.width, args.height);
+
+    let instance = create_instance(&InstanceConfig::headless())?;
+    let float64 = Features {
+        shader_float64: true,
+        .. Features::none()
+    };
+    // Everything runs in a compute shader, so devices without graphics queues are fine too.
+    let requirements = DeviceRequirements {
+        graphics: false,
+        compute: true,
+        .. DeviceRequirements::default()
+    };
+    let initializer = VulkanInit::builder(&instance)
+        .requirements(requirements)
+        .optional_features(float64)
+        .build()?;
+
+    let device = initializer.device.clone();
+    let queue = initializer.compute.clone();
+
+    /*
+    let source_content = 0..64;
+    let source = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), source_content)
+        .expect("Failed to create source buffer.");
+
+    let dest_content = (0..64).map(|_| 0);
+    let dest = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), dest_content)
+        .expect("Failed to created destination buffer.");
+
+    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .copy_buffer(source.clone(), dest.clone()).unwrap()
+        .build().unwrap();
+
+    let finished = command_buffer.execute(queue.clone()).unwrap();
+    finished.then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    let src_content = source.read().unwrap();
+    let dest_content = dest.read().unwrap();
+
+    assert_eq!(&*src_content, &*dest_content)
+    */
+
+    /*
+    let data_iter = 0..65536;
+    let data_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), data_iter).unwrap();
+
+    let shader = cs::Shader::load(device.clone()).unwrap();
+    let compute_pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+                                    .expect("Failed to create compute pipeline"));
+
+    let set = Arc::new(PersistentDescriptorSet::start(compute_pipeline.clone(), 0)
+                       .add_buffer(data_buffer.clone()).unwrap()
+                       .build().unwrap()
+                      );
+
+    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch([1024, 1, 1], compute_pipeline.clone(), set.clone(), ()).unwrap()
+        .build().unwrap();
+
+    let finished = command_buffer.execute(queue.clone()).unwrap();
+    finished.then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    let content = data_buffer.read().unwrap();
+    for (n, val) in content.iter().enumerate() {
+        assert_eq!(*val, n as u32 * 12);
+    }
+
+    println!("Success!")
+    */
+
+    let image = StorageImage::new(device.clone(), 
+                                  Dimensions::Dim2d{ width: width, height: height },
+                                  Format::R8G8B8A8Unorm,
+                                  Some(queue.family()))?;
+
+
+    let mandelbrot = MandelbrotPipeline::new(&device)?;
+    let params = args.params.with_size(width, height);
+    let palette = args.palette.upload(&device)?;
+    let precision = args.precision.unwrap_or_else(|| mandelbrot.precision_for(&params, height));
+    println!("Rendering in {:?} precision", precision);
+    if params.pixels_per_unit(height) > precision.max_pixels_per_unit() {
+        eprintln!("Warning: {:?} precision can't resolve a zoom of {:e} at this size, the image will be blocky or \
+                   a single color.", precision, params.zoom);
+        if !mandelbrot.supports(Precision::Double, FractalKind::Mandelbrot) {
+            eprintln!("The device doesn't support shader_float64, which deeper zooms need.");
+        } else if !mandelbrot.supports(Precision::Double, params.kind) {
+            eprintln!("Deeper zooms of Multibrot sets need a whole power.");
+        }
+    }
+
+    let size = (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| format!("A {}x{} image is too large", width, height))?;
+    let buf = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), (0 .. size).map(|_| 0u8))?;
+
+    // Separate command buffers, so the profiler can time the dispatch and the copy on their own.
+    let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family())?;
+    let dispatch = match precision {
+        Precision::Single => mandelbrot.dispatch(builder, image.clone(), palette.clone(), &params)?,
+        Precision::Double => mandelbrot.dispatch_double(builder, image.clone(), palette.clone(), &params)?,
+        Precision::Perturbation => {
+            let frac_limbs = ReferenceOrbit::frac_limbs_for(&params, height);
+            let center = [parse_fixed_point(&args.center_text[0], frac_limbs)?,
+                          parse_fixed_point(&args.center_text[1], frac_limbs)?];
+            let orbit = ReferenceOrbit::compute(&center, &params);
+            println!("Reference orbit has {} points at {} bits", orbit.points.len(), frac_limbs * 32);
+            mandelbrot.dispatch_perturbed(builder, image.clone(), palette.clone(), orbit.upload(&device)?, &params)?
+        }
+    }.build()?;
+    let copy = AutoCommandBufferBuilder::new(device.clone(), queue.family())?
+        .copy_image_to_buffer(image.clone(), buf.clone())?
+        .build()?;
+
+    let mut profiler = Profiler::new(&device, queue.family(), 2);
+    let finished = profiler.execute_scope(sync::now(device.clone()), &queue, "dispatch", dispatch)?;
+    let finished = profiler.execute_scope(finished, &queue, "copy", copy)?;
+    finished.then_signal_fence_and_flush()?.wait(None)?;
+
+    for scope in profiler.resolve() {
+        println!("{}: {:.3} ms", scope.name, duration_ms(scope.duration));
+    }
+
+    let buffer_content = buf.read()?;
+    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, &buffer_content[..]).unwrap();
+
+    image.save(&args.output)?;
+    println!("Wrote {}", args.output.display());
+
+    Ok(())
+}
+
+fn usage() -> String {
+    USAGE.replace("{palettes}", &Palette::builtin_names().join(", "))
+        .replace("{fractals}", &FractalKind::NAMES.join(", "))
+        .replace("{precisions}", &Precision::NAMES.join(", "))
+}
+
+/// `Ok(None)` if `--help` was passed.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        width: 1024,
+        height: 1024,
+        params: MandelbrotParams::default(),
+        center_text: [String::new(), String::new()],
+        precision: None,
+        palette: Palette::default(),
+        output: PathBuf::from("image.png"),
+    };
+    // The kind's own values are only known once all options are read.
+    let mut kind = FractalKind::Mandelbrot;
+    let mut center = None;
+    let mut julia_c = None;
+    let mut power = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" => return Ok(None),
+            "--no-smooth" => {
+                parsed.params.smooth_coloring = false;
+                continue;
+            }
+            _ => (),
+        }
+
+        let value = args.next().ok_or_else(|| format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "-w" | "--width" => parsed.width = parse_size(&arg, &value)?,
+            "-h" | "--height" => parsed.height = parse_size(&arg, &value)?,
+            "-f" | "--fractal" => {
+                kind = FractalKind::from_name(&value).ok_or_else(|| format!("Unknown fractal {:?}", value))?;
+            }
+            "--julia-c" => julia_c = Some(parse_complex(&arg, &value)?),
+            "--power" => {
+                power = match value.parse::<f32>() {
+                    Ok(power) if power > 1.0 && power <= MAX_MULTIBROT_POWER => Some(power),
+                    _ => return Err(format!("Invalid power {:?}, expected a number above 1 up to {}",
+                                            value, MAX_MULTIBROT_POWER)),
+                };
+            }
+            "-c" | "--center" => {
+                let parsed_center = parse_complex(&arg, &value)?;
+                let mut parts = value.split(',').map(|v| v.trim().to_owned());
+                parsed.center_text = [parts.next().unwrap_or_default(), parts.next().unwrap_or_default()];
+                center = Some(parsed_center);
+            }
+            "--precision" => {
+                parsed.precision = Some(Precision::from_name(&value).ok_or_else(|| format!("Unknown precision {:?}", value))?);
+            }
+            "-z" | "--zoom" => {
+                parsed.params.zoom = match value.parse::<f64>() {
+                    Ok(zoom) if zoom > 0.0 && zoom.is_finite() => zoom,
+                    _ => return Err(format!("Invalid zoom {:?}, expected a positive number", value)),
+                };
+            }
+            "-i" | "--iterations" => {
+                parsed.params.max_iterations = match value.parse::<u32>() {
+                    Ok(iterations) if iterations > 0 => iterations,
+                    _ => return Err(format!("Invalid iteration count {:?}", value)),
+                };
+            }
+            "-p" | "--palette" => {
+                parsed.palette = Palette::from_name_or_file(&value)
+                    .map_err(|err| format!("Couldn't load palette {:?}: {}", value, err))?;
+            }
+            "--palette-offset" => parsed.params.palette_offset = parse_float(&arg, &value)?,
+            "--palette-density" => parsed.params.palette_density = parse_float(&arg, &value)?,
+            "-o" | "--output" => parsed.output = PathBuf::from(value),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+
+    parsed.params.kind = match kind {
+        FractalKind::Julia { c } => FractalKind::Julia { c: julia_c.unwrap_or(c) },
+        FractalKind::Multibrot { power: default } => FractalKind::Multibrot { power: power.unwrap_or(default) },
+        kind => kind,
+    };
+    if center.is_none() {
+        let default = kind.default_center();
+        parsed.center_text = [default[0].to_string(), default[1].to_string()];
+    }
+    parsed.params.center = center.unwrap_or_else(|| kind.default_center());
+
+    Ok(Some(parsed))
+}
+
+fn parse_complex(arg: &str, value: &str) -> Result<[f64; 2], String> {
+    let parts = value.split(',').map(|v| v.trim().parse::<f64>()).collect::<Vec<_>>();
+    match parts[..] {
+        [Ok(re), Ok(im)] => Ok([re, im]),
+        _ => Err(format!("Invalid value {:?} for {}, expected <re>,<im>", value, arg)),
+    }
+}
+
+fn parse_fixed_point(value: &str, frac_limbs: usize) -> Result<FixedPoint, String> {
+    FixedPoint::parse(value, frac_limbs).ok_or_else(|| format!("Invalid center coordinate {:?}", value))
+}
+
+fn parse_float(arg: &str, value: &str) -> Result<f32, String> {
+    value.parse::<f32>().map_err(|_| format!("Invalid value {:?} for {}, expected a number", value, arg))
+}
+
+fn parse_size(arg: &str, value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!("Invalid value {:?} for {}, expected a positive integer", value, arg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults() {
+        let args = parse(&[]).unwrap().unwrap();
+        assert_eq!((args.width, args.height), (1024, 1024));
+        assert_eq!(args.params, MandelbrotParams::default());
+        assert_eq!(args.palette, Palette::default());
+        assert_eq!(args.output, PathBuf::from("image.png"));
+        assert_eq!(args.precision, None);
+    }
+
+    #[test]
+    fn help() {
+        assert!(parse(&["-w", "10", "--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn no_smooth_takes_no_value() {
+        let args = parse(&["--no-smooth", "-w", "640"]).unwrap().unwrap();
+        assert!(!args.params.smooth_coloring);
+        assert_eq!(args.width, 640);
+    }
+
+    #[test]
+    fn missing_value() {
+        assert!(parse(&["--zoom"]).is_err());
+    }
+
+    #[test]
+    fn center() {
+        let args = parse(&["-c", "0.25, -0.5"]).unwrap().unwrap();
+        assert_eq!(args.params.center, [0.25, -0.5]);
+        assert_eq!(args.center_text, ["0.25".to_owned(), "-0.5".to_owned()]);
+
+        assert!(parse(&["-c", "0.25"]).is_err());
+        assert!(parse(&["-c", "a,b"]).is_err());
+        assert!(parse(&["-c", "1,2,3"]).is_err());
+    }
+
+    #[test]
+    fn fractal_defaults() {
+        let args = parse(&["--fractal", "julia", "--julia-c", "0.1,0.2"]).unwrap().unwrap();
+        assert_eq!(args.params.kind, FractalKind::Julia { c: [0.1, 0.2] });
+        assert_eq!(args.params.center, FractalKind::Julia { c: [0.1, 0.2] }.default_center());
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert!(parse(&["-w", "0"]).is_err());
+        assert!(parse(&["-z", "-1"]).is_err());
+        assert!(parse(&["-i", "many"]).is_err());
+        assert!(parse(&["--bogus", "1"]).is_err());
+        for power in &["1", "0", "-2", "NaN", "inf", "65"] {
+            assert!(parse(&["-f", "multibrot", "--power", power]).is_err(), "{}", power);
+        }
+    }
+}
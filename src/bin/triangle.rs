@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use std::error::Error;
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::format::Format;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::framebuffer::FramebufferAbstract;
+use vulkano::framebuffer::RenderPassAbstract;
+use vulkano::format::ClearValue;
+use vulkano::image::AttachmentImage;
+use vulkano::image::StorageImage;
+use vulkano::image::Dimensions;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::framebuffer::Subpass;
+use vulkano::command_buffer::DynamicState;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::sync;
+use vulkano::sync::GpuFuture;
+use image::{ImageBuffer, Rgba};
+
+use vulkano_tutorial::{VulkanInit, InstanceConfig, InitError, Profiler, create_instance};
+use vulkano_tutorial::frame_timer::duration_ms;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location = 0) in vec2 position;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(0.3, 0.2, 0.0, 1.0);
+}
+"
+    }
+}
+
+/// Lowered to what the device supports. 1 renders straight into the output image.
+const SAMPLES: u32 = 4;
+
+struct Vertex {
+    position: [f32; 2], 
+}
+
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+
+    let instance = create_instance(&InstanceConfig::headless())?;
+    let initializer = VulkanInit::create(&instance)?;
+
+    let device = initializer.device.clone();
+    let queue = initializer.graphics.clone();
+
+    vulkano::impl_vertex!(Vertex, position);
+
+    let vertex1 = Vertex { position: [-0.9,  0.0] };
+    let vertex2 = Vertex { position: [ 0.9,  0.0] };
+    let vertex3 = Vertex { position: [ 0.0, -0.9] };
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), 
+                                                       BufferUsage::all(),
+                                                       vec![vertex1, vertex2, vertex3].into_iter()
+                                                       )?;
+
+
+    let image = StorageImage::new(device.clone(), 
+                                  Dimensions::Dim2d{ width: 1024, height: 1024 },
+                                  Format::R8G8B8A8Unorm,
+                                  Some(queue.family()))?;
+
+    let buf = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), (0..1024*1024*4).map(|_|0u8))?;
+
+
+    let samples = initializer.supported_sample_count(SAMPLES, false);
+
+    // With MSAA we draw into a multisampled image and resolve it into `image` at the end of the pass.
+    let (render_pass, framebuffer, clear_values) = if samples > 1 {
+        let render_pass = Arc::new(vulkano::single_pass_renderpass!(device.clone(), 
+            attachments: {
+                intermediary: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: samples,
+                },
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [intermediary],
+                depth_stencil: {}
+                resolve: [color],
+            })?);
+
+        let intermediary = AttachmentImage::transient_multisampled(device.clone(), [1024, 1024], samples,
+                                                                   Format::R8G8B8A8Unorm)?;
+
+        let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+                                   .add(intermediary.clone())?
+                                   .add(image.clone())?
+                                   .build()?);
+
+        (render_pass as Arc<RenderPassAbstract + Send + Sync>,
+         framebuffer as Arc<FramebufferAbstract + Send + Sync>,
+         vec![[0.3, 0.5, 1.0, 1.0].into(), ClearValue::None])
+    } else {
+        let render_pass = Arc::new(vulkano::single_pass_renderpass!(device.clone(), 
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            })?);
+
+        let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+                                   .add(image.clone())?
+                                   .build()?);
+
+        (render_pass as Arc<RenderPassAbstract + Send + Sync>,
+         framebuffer as Arc<FramebufferAbstract + Send + Sync>,
+         vec![[0.3, 0.5, 1.0, 1.0].into()])
+    };
+
+    let vs = vs::Shader::load(device.clone()).map_err(InitError::ShaderLoadFailed)?;
+    let fs = fs::Shader::load(device.clone()).map_err(InitError::ShaderLoadFailed)?;
+
+    let pipeline = Arc::new(GraphicsPipeline::start()
+                            .vertex_input_single_buffer::<Vertex>()
+                            .vertex_shader(vs.main_entry_point(), ())
+                            .viewports_dynamic_scissors_irrelevant(1)
+                            .fragment_shader(fs.main_entry_point(), ())
+                            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                            .build(device.clone())?
+                            );
+
+    let dynamic_state = DynamicState {
+        viewports: Some(vec![ Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [1024.0, 1024.0],
+            depth_range: 0.0 .. 1.0
+        }]),
+        .. DynamicState::none()
+    };
+
+
+    let draw = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())?
+        .begin_render_pass(framebuffer.clone(), false, clear_values)?
+
+        .draw(pipeline.clone(), &dynamic_state, vertex_buffer.clone(), (), ())?
+
+        .end_render_pass()?
+
+        .build()?;
+
+    let copy = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())?
+        .copy_image_to_buffer(image.clone(), buf.clone())?
+        .build()?;
+
+    let mut profiler = Profiler::new(&device, queue.family(), 2);
+    let finished = profiler.execute_scope(sync::now(device.clone()), &queue, "draw", draw)?;
+    let finished = profiler.execute_scope(finished, &queue, "copy", copy)?;
+    finished.then_signal_fence_and_flush()?
+        .wait(None)?;
+
+    for scope in profiler.resolve() {
+        println!("{}: {:.3} ms", scope.name, duration_ms(scope.duration));
+    }
+
+    let buffer_content = buf.read()?;
+    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(1024, 1024, &buffer_content[..]).unwrap();
+
+    image.save("triangle.png")?;
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use vulkano::device::Device;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::vertex::VertexSource;
+use vulkano::framebuffer::Subpass;
+use vulkano::framebuffer::RenderPassAbstract;
+use vulkano::framebuffer::FramebufferAbstract;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::buffer::BufferUsage;
+use vulkano::swapchain::PresentMode;
+
+use vulkano_tutorial::{VulkanWindow, VulkanInit, InstanceConfig, InitError, WindowConfig, Renderer, FrameStatus, FrameTimer, create_instance};
+use vulkano_tutorial::{InputState, KeyBindings, Action};
+use vulkano_tutorial::DEFAULT_FRAMES_IN_FLIGHT;
+use vulkano_tutorial::renderer::viewport_state;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+layout(location = 0) out vec4 v_color;
+
+
+void main() {
+    v_color = color;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec4 v_color;
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = v_color;
+}
+"
+    }
+}
+
+struct Vertex {
+    position: [f32; 2], 
+    color: [f32; 4]
+}
+
+vulkano::impl_vertex!(Vertex, position, color);
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+
+    // Step 1: Create Instance
+    let instance = create_instance(&InstanceConfig::windowed())?;
+    let mut window_config = WindowConfig::default();
+    // Lets F12 copy the swapchain image out for screenshots.
+    window_config.image_usage.transfer_source = true;
+    let (events_loop, surface) = VulkanWindow::create_surface(&instance, &window_config)?;
+    let initializer = VulkanInit::builder(&instance).present_surface(&surface).build()?;
+
+
+    // Needs to be mutable to poll events loop.
+    let mut window_data = VulkanWindow::create(&initializer, events_loop, surface, window_config)?;
+    println!("Swapchain format: {:?} (hardware sRGB: {})", window_data.surface_format, window_data.surface_format.is_srgb());
+
+    // Step 5: Create vertex buffer
+    let vertex1 = Vertex { position: [-0.9,  0.0], color: [0.8, 0.2, 0.2, 1.0] };
+    let vertex2 = Vertex { position: [ 0.9,  0.0], color: [0.2, 0.8, 0.2, 1.0] };
+    let vertex3 = Vertex { position: [ 0.0, -0.9], color: [0.2, 0.2, 0.8, 1.0] };
+
+    let vertex4 = Vertex { position: [ 0.0,  0.9], color: [0.8, 0.8, 0.2, 1.0] };
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(initializer.device.clone(), 
+                                                       BufferUsage::vertex_buffer(),
+                                                       vec![vertex1, vertex2, vertex3, vertex4].into_iter()
+                                                       )?;
+
+    let index_buffer = CpuAccessibleBuffer::from_iter(initializer.device.clone(), BufferUsage::index_buffer(),
+                                                      vec![0, 1, 2, 0, 3, 1].into_iter().map(|x| x as u32))?;
+
+
+
+    // Step 9: Load the shader
+    let vs = vs::Shader::load(initializer.device.clone()).map_err(InitError::ShaderLoadFailed)?;
+    let fs = fs::Shader::load(initializer.device.clone()).map_err(InitError::ShaderLoadFailed)?;
+
+
+    // Step 11: Setup graphics pipeline. The viewport is dynamic, so this survives resizes.
+    let pipeline = create_pipeline(&initializer.device, &window_data.render_pass, &vs, &fs)?;
+
+    let mut renderer = Renderer::new(&initializer, DEFAULT_FRAMES_IN_FLIGHT)?;
+    renderer.clear_color = [0.02, 0.02, 0.02, 1.0];
+
+    let (mut frame_timer, csv_path) = FrameTimer::from_env();
+    let mut input = InputState::new();
+    let bindings = KeyBindings::default();
+    let mut last_title_update = Instant::now();
+
+    loop {
+        frame_timer.tick();
+
+        input.begin_frame();
+        window_data.poll_events(|event| input.handle_event(&event));
+
+        let mut done = input.close_requested();
+        for action in bindings.triggered(&input) {
+            match action {
+                Action::Quit => done = true,
+                Action::ToggleFullscreen => window_data.toggle_fullscreen(),
+                Action::Screenshot => {
+                    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                    renderer.request_screenshot(format!("screenshot-{}.png", timestamp));
+                }
+                Action::ToggleVsync => {
+                    let present_modes = if window_data.present_mode == PresentMode::Fifo {
+                        vec![PresentMode::Mailbox, PresentMode::Immediate, PresentMode::Fifo]
+                    } else {
+                        vec![PresentMode::Fifo]
+                    };
+                    window_data.set_present_modes(present_modes);
+                }
+            }
+        }
+
+        if done {
+            renderer.wait_idle()?;
+            if let Some(path) = csv_path {
+                frame_timer.write_csv(&path)?;
+                println!("Wrote frame times to {}", path.display());
+            }
+            return Ok(());
+        }
+
+        let status = renderer.draw_frame(&mut window_data, |builder, framebuffer| {
+            let dynamic_state = viewport_state([framebuffer.width(), framebuffer.height()]);
+            Ok(builder.draw_indexed(pipeline.clone(), &dynamic_state, vertex_buffer.clone(), index_buffer.clone(), (), ())?)
+        })?;
+
+        if let Some(cpu) = renderer.last_cpu_time() {
+            frame_timer.record_cpu(cpu);
+        }
+        if let Some(gpu) = renderer.last_gpu_time() {
+            frame_timer.record_gpu(gpu);
+        }
+
+        // Updating the title every frame would make it unreadable.
+        if last_title_update.elapsed() > Duration::from_millis(500) {
+            let title = frame_timer.title(&window_data.config.title);
+            window_data.window().set_title(&title);
+            last_title_update = Instant::now();
+        }
+
+        // Nothing to draw into until the window is restored.
+        if status == FrameStatus::Minimized {
+            thread::sleep(Duration::from_millis(16));
+        }
+    }
+}
+
+/// The quad's pipeline. Only depends on the render pass, which stays the same when the swapchain is
+/// recreated.
+fn create_pipeline(device: &Arc<Device>, render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+                   vs: &vs::Shader, fs: &fs::Shader)
+    -> Result<Arc<impl GraphicsPipelineAbstract + VertexSource<Arc<CpuAccessibleBuffer<[Vertex]>>> + Send + Sync>, InitError>
+{
+    let pipeline = GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())?;
+    Ok(Arc::new(pipeline))
+}
@@ -0,0 +1,83 @@
+use std::env;
+use std::sync::Arc;
+use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+use vulkano::instance::layers_list;
+use vulkano::instance::debug::{DebugCallback, Message, MessageTypes};
+
+/// Setting this to anything but `0` turns on validation even without the `validation` feature.
+pub const VALIDATION_ENV_VAR: &str = "VULKANO_TUTORIAL_VALIDATION";
+
+/// Tried in order. The LunarG meta layer is what older SDKs ship instead of the Khronos one.
+const VALIDATION_LAYERS: &[&str] = &["VK_LAYER_KHRONOS_validation", "VK_LAYER_LUNARG_standard_validation"];
+
+/// Whether validation was asked for, through the `validation` cargo feature or `VULKANO_TUTORIAL_VALIDATION`.
+pub fn validation_requested() -> bool {
+    let from_env = match env::var(VALIDATION_ENV_VAR) {
+        Ok(value) => value != "0" && !value.is_empty(),
+        Err(_) => false,
+    };
+    cfg!(feature = "validation") || from_env
+}
+
+/// The first validation layer that is actually installed, if any.
+pub fn available_validation_layer() -> Option<String> {
+    let available = match layers_list() {
+        Ok(layers) => layers.map(|l| l.name().to_owned()).collect::<Vec<_>>(),
+        Err(err) => {
+            log::warn!("Couldn't list instance layers: {}", err);
+            return None;
+        }
+    };
+
+    let layer = VALIDATION_LAYERS.iter().find(|&&name| available.iter().any(|l| l == name));
+    if layer.is_none() {
+        log::warn!("Validation was requested but none of {:?} are installed.", VALIDATION_LAYERS);
+    }
+    layer.map(|&name| name.to_owned())
+}
+
+/// The extensions needed to receive debug messages, if the implementation has them.
+pub fn debug_extensions() -> InstanceExtensions {
+    let supported = InstanceExtensions::supported_by_core().unwrap_or_else(|_| InstanceExtensions::none());
+    InstanceExtensions {
+        ext_debug_report: supported.ext_debug_report,
+        .. InstanceExtensions::none()
+    }
+}
+
+/// Forwards every validation message into the `log` crate at a matching level. Returns `None` if the
+/// instance wasn't created with `ext_debug_report`. The callback is unregistered when dropped.
+pub fn register_debug_callback(instance: &Arc<Instance>) -> Option<DebugCallback> {
+    if !instance.loaded_extensions().ext_debug_report {
+        return None;
+    }
+
+    let types = MessageTypes {
+        error: true,
+        warning: true,
+        performance_warning: true,
+        information: true,
+        debug: true,
+    };
+
+    let callback = DebugCallback::new(instance, types, |msg: &Message| {
+        if msg.ty.error {
+            log::error!("[{}] {}", msg.layer_prefix, msg.description);
+        } else if msg.ty.warning || msg.ty.performance_warning {
+            log::warn!("[{}] {}", msg.layer_prefix, msg.description);
+        } else if msg.ty.information {
+            log::info!("[{}] {}", msg.layer_prefix, msg.description);
+        } else {
+            log::debug!("[{}] {}", msg.layer_prefix, msg.description);
+        }
+    });
+
+    match callback {
+        Ok(callback) => Some(callback),
+        Err(err) => {
+            log::warn!("Failed to register debug callback: {}", err);
+            None
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::env;
+use std::sync::Arc;
+use std::fmt;
+use std::error::Error;
+use vulkano::instance::Instance;
+use vulkano::instance::PhysicalDevice;
+use vulkano::instance::PhysicalDeviceType;
+use vulkano::device::DeviceExtensions;
+use vulkano::device::Features;
+use vulkano::swapchain::Surface;
+use winit::Window;
+
+/// Name of the environment variable read by `DeviceSelection::FromEnv`. It can hold either a device
+/// index or a (case insensitive) substring of the device name.
+pub const DEVICE_ENV_VAR: &str = "VULKANO_TUTORIAL_DEVICE";
+
+/// How to choose between the physical devices that meet the `DeviceRequirements`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelection {
+    /// The first suitable device, in enumeration order.
+    First,
+    /// Discrete GPUs over integrated over virtual over software rasterizers.
+    PreferDiscrete,
+    /// The first suitable device whose name contains this substring (case insensitive).
+    ByName(String),
+    /// The device at this index in `PhysicalDevice::enumerate`.
+    ByIndex(usize),
+    /// Read `VULKANO_TUTORIAL_DEVICE`, falling back to `PreferDiscrete` if it isn't set.
+    FromEnv,
+}
+
+impl Default for DeviceSelection {
+    fn default() -> DeviceSelection {
+        DeviceSelection::FromEnv
+    }
+}
+
+impl DeviceSelection {
+    /// Resolves `FromEnv` into one of the other policies.
+    fn resolve(&self) -> DeviceSelection {
+        match self {
+            DeviceSelection::FromEnv => match env::var(DEVICE_ENV_VAR) {
+                Ok(ref value) if value.trim().is_empty() => DeviceSelection::PreferDiscrete,
+                Ok(value) => match value.trim().parse::<usize>() {
+                    Ok(index) => DeviceSelection::ByIndex(index),
+                    Err(_) => DeviceSelection::ByName(value.trim().to_owned()),
+                },
+                Err(_) => DeviceSelection::PreferDiscrete,
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+/// What a physical device has to support to be considered at all.
+#[derive(Debug, Clone)]
+pub struct DeviceRequirements {
+    pub graphics: bool,
+    pub compute: bool,
+    pub features: Features,
+    pub extensions: DeviceExtensions,
+}
+
+impl Default for DeviceRequirements {
+    fn default() -> DeviceRequirements {
+        DeviceRequirements {
+            graphics: true,
+            compute: false,
+            features: Features::none(),
+            extensions: DeviceExtensions::none(),
+        }
+    }
+}
+
+/// A device that was skipped during selection, and why.
+#[derive(Debug, Clone)]
+pub struct RejectedDevice {
+    pub index: usize,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeviceSelectionError {
+    pub selection: DeviceSelection,
+    pub rejected: Vec<RejectedDevice>,
+}
+
+impl fmt::Display for DeviceSelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.rejected.is_empty() {
+            return write!(f, "No physical devices available.");
+        }
+
+        write!(f, "No suitable physical device found using {:?}:", self.selection)?;
+        for device in &self.rejected {
+            write!(f, "\n  [{}] {}: {}", device.index, device.name, device.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for DeviceSelectionError {}
+
+/// Names of the features that are enabled in `features`, e.g. `["shader_float64"]`.
+pub fn feature_names(features: &Features) -> Vec<String> {
+    // `Features` has no way to iterate over its fields, so pick the enabled ones out of the
+    // derived `Debug` output (`Features { robust_buffer_access: false, ... }`).
+    let debug = format!("{:?}", features);
+    let fields = debug.trim_start_matches("Features").trim_matches(|c| c == ' ' || c == '{' || c == '}');
+
+    fields.split(',')
+        .filter_map(|field| {
+            let mut parts = field.split(':');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) if value.trim() == "true" => Some(name.trim().to_owned()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// `Features` only provides `intersection` and `difference`, so build the union out of those.
+pub fn union_features(a: &Features, b: &Features) -> Features {
+    let all = Features::all();
+    all.difference(&all.difference(a).intersection(&all.difference(b)))
+}
+
+/// Checks `physical` against `requirements`, and whether it can present to `surface` if there is
+/// one. Returns a score (higher is better) or the reason the device can't be used.
+pub fn score_device(physical: PhysicalDevice, requirements: &DeviceRequirements,
+                    surface: Option<&Arc<Surface<Arc<Window>>>>)
+    -> Result<u32, String>
+{
+    if requirements.graphics && !physical.queue_families().any(|q| q.supports_graphics()) {
+        return Err("no graphics queue family".to_owned());
+    }
+
+    if let Some(surface) = surface {
+        // A failed query is as good as no support, the surface can't be used from this device either way.
+        if !physical.queue_families().any(|q| surface.is_supported(q).unwrap_or(false)) {
+            return Err("no queue family can present to the surface".to_owned());
+        }
+    }
+
+    if requirements.compute && !physical.queue_families().any(|q| q.supports_compute()) {
+        return Err("no compute queue family".to_owned());
+    }
+
+    let supported_features = physical.supported_features();
+    if !supported_features.superset_of(&requirements.features) {
+        let missing = requirements.features.difference(supported_features);
+        return Err(format!("missing features {:?}", feature_names(&missing)));
+    }
+
+    let supported_extensions = DeviceExtensions::supported_by_device(physical);
+    let missing = requirements.extensions.difference(&supported_extensions);
+    if missing != DeviceExtensions::none() {
+        return Err(format!("missing extensions {:?}", missing));
+    }
+
+    let score = match physical.ty() {
+        PhysicalDeviceType::DiscreteGpu => 4,
+        PhysicalDeviceType::IntegratedGpu => 3,
+        PhysicalDeviceType::VirtualGpu => 2,
+        PhysicalDeviceType::Cpu => 1,
+        PhysicalDeviceType::Other => 0,
+    };
+
+    Ok(score)
+}
+
+/// Picks a physical device according to `selection`, skipping any that don't meet `requirements`
+/// or can't present to `surface`.
+pub fn select_physical_device<'a>(instance: &'a Arc<Instance>,
+                                  selection: &DeviceSelection,
+                                  requirements: &DeviceRequirements,
+                                  surface: Option<&Arc<Surface<Arc<Window>>>>)
+    -> Result<PhysicalDevice<'a>, DeviceSelectionError>
+{
+    let policy = selection.resolve();
+
+    let mut rejected = Vec::new();
+    let mut best: Option<(PhysicalDevice<'a>, u32)> = None;
+
+    for physical in PhysicalDevice::enumerate(instance) {
+        let matches = match policy {
+            DeviceSelection::ByName(ref name) => physical.name().to_lowercase().contains(&name.to_lowercase()),
+            DeviceSelection::ByIndex(index) => physical.index() == index,
+            _ => true,
+        };
+
+        if !matches {
+            rejected.push(RejectedDevice {
+                index: physical.index(),
+                name: physical.name(),
+                reason: format!("does not match {:?}", policy),
+            });
+            continue;
+        }
+
+        match score_device(physical, requirements, surface) {
+            Ok(score) => {
+                // Everything scores the same unless we prefer discrete devices, so ties keep the
+                // earliest device.
+                let score = if policy == DeviceSelection::PreferDiscrete { score } else { 0 };
+                if best.map_or(true, |(_, best_score)| score > best_score) {
+                    best = Some((physical, score));
+                }
+            }
+            Err(reason) => rejected.push(RejectedDevice {
+                index: physical.index(),
+                name: physical.name(),
+                reason: reason,
+            }),
+        }
+    }
+
+    match best {
+        Some((physical, _)) => Ok(physical),
+        None => Err(DeviceSelectionError { selection: policy, rejected: rejected }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_names_lists_enabled_features() {
+        let features = Features {
+            shader_float64: true,
+            .. Features::none()
+        };
+        assert_eq!(feature_names(&features), vec!["shader_float64".to_owned()]);
+        assert!(feature_names(&Features::none()).is_empty());
+    }
+
+    #[test]
+    fn union_features_keeps_both_sides() {
+        let a = Features {
+            shader_float64: true,
+            .. Features::none()
+        };
+        let b = Features {
+            geometry_shader: true,
+            .. Features::none()
+        };
+        let union = union_features(&a, &b);
+        assert!(union.superset_of(&a));
+        assert!(union.superset_of(&b));
+        assert_eq!(feature_names(&union).len(), 2);
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+use std::error::Error;
+use vulkano::OomError;
+use vulkano::instance::InstanceCreationError;
+use vulkano::device::DeviceCreationError;
+use vulkano::swapchain::CapabilitiesError;
+use vulkano::swapchain::SwapchainCreationError;
+use vulkano::framebuffer::RenderPassCreationError;
+use vulkano::framebuffer::FramebufferCreationError;
+use vulkano::pipeline::GraphicsPipelineCreationError;
+use vulkano::pipeline::ComputePipelineCreationError;
+use vulkano::image::ImageCreationError;
+
+use crate::device_selection::DeviceSelectionError;
+
+/// Everything that can go wrong while setting up the instance, device, window or pipelines.
+#[derive(Debug)]
+pub enum InitError {
+    /// The Vulkan library couldn't be loaded, or the requested extensions/layers aren't available.
+    NoInstance(InstanceCreationError),
+    /// None of the physical devices are usable.
+    NoDevice(DeviceSelectionError),
+    NoGraphicsQueue,
+    DeviceCreationFailed(DeviceCreationError),
+    SurfaceCreationFailed(vulkano_win::CreationError),
+    /// The surface can't be presented to from this device, or its capabilities couldn't be queried.
+    SurfaceUnsupported(Option<CapabilitiesError>),
+    /// The window was closed before we could read its size.
+    WindowClosed,
+    SwapchainFailed(SwapchainCreationError),
+    RenderPassFailed(RenderPassCreationError),
+    FramebufferFailed(FramebufferCreationError),
+    /// An attachment such as the depth buffer couldn't be created.
+    ImageFailed(ImageCreationError),
+    ShaderLoadFailed(OomError),
+    PipelineFailed(GraphicsPipelineCreationError),
+    ComputePipelineFailed(ComputePipelineCreationError),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InitError::NoInstance(err) => write!(f, "Failed to create instance: {}", err),
+            InitError::NoDevice(err) => write!(f, "{}", err),
+            InitError::NoGraphicsQueue => write!(f, "Couldn't find a graphical queue family."),
+            InitError::DeviceCreationFailed(err) => write!(f, "Failed to create device: {}", err),
+            InitError::SurfaceCreationFailed(err) => write!(f, "Failed to create window surface: {}", err),
+            InitError::SurfaceUnsupported(Some(err)) => write!(f, "Surface is not supported: {}", err),
+            InitError::SurfaceUnsupported(None) => write!(f, "Surface is not supported by this device."),
+            InitError::WindowClosed => write!(f, "Unable to get window dimensions, the window no longer exists."),
+            InitError::SwapchainFailed(err) => write!(f, "Failed to create swapchain: {}", err),
+            InitError::RenderPassFailed(err) => write!(f, "Failed to create render pass: {}", err),
+            InitError::FramebufferFailed(err) => write!(f, "Failed to create framebuffer: {}", err),
+            InitError::ImageFailed(err) => write!(f, "Failed to create image: {}", err),
+            InitError::ShaderLoadFailed(err) => write!(f, "Failed to create shader module: {}", err),
+            InitError::PipelineFailed(err) => write!(f, "Failed to create pipeline: {}", err),
+            InitError::ComputePipelineFailed(err) => write!(f, "Failed to create compute pipeline: {}", err),
+        }
+    }
+}
+
+impl Error for InitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InitError::NoInstance(err) => Some(err),
+            InitError::NoDevice(err) => Some(err),
+            InitError::DeviceCreationFailed(err) => Some(err),
+            InitError::SurfaceCreationFailed(err) => Some(err),
+            InitError::SurfaceUnsupported(Some(err)) => Some(err),
+            InitError::SwapchainFailed(err) => Some(err),
+            InitError::RenderPassFailed(err) => Some(err),
+            InitError::FramebufferFailed(err) => Some(err),
+            InitError::ImageFailed(err) => Some(err),
+            InitError::ShaderLoadFailed(err) => Some(err),
+            InitError::PipelineFailed(err) => Some(err),
+            InitError::ComputePipelineFailed(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<InstanceCreationError> for InitError {
+    fn from(err: InstanceCreationError) -> InitError {
+        InitError::NoInstance(err)
+    }
+}
+
+impl From<DeviceSelectionError> for InitError {
+    fn from(err: DeviceSelectionError) -> InitError {
+        InitError::NoDevice(err)
+    }
+}
+
+impl From<DeviceCreationError> for InitError {
+    fn from(err: DeviceCreationError) -> InitError {
+        InitError::DeviceCreationFailed(err)
+    }
+}
+
+impl From<vulkano_win::CreationError> for InitError {
+    fn from(err: vulkano_win::CreationError) -> InitError {
+        InitError::SurfaceCreationFailed(err)
+    }
+}
+
+impl From<CapabilitiesError> for InitError {
+    fn from(err: CapabilitiesError) -> InitError {
+        InitError::SurfaceUnsupported(Some(err))
+    }
+}
+
+impl From<SwapchainCreationError> for InitError {
+    fn from(err: SwapchainCreationError) -> InitError {
+        InitError::SwapchainFailed(err)
+    }
+}
+
+impl From<RenderPassCreationError> for InitError {
+    fn from(err: RenderPassCreationError) -> InitError {
+        InitError::RenderPassFailed(err)
+    }
+}
+
+impl From<FramebufferCreationError> for InitError {
+    fn from(err: FramebufferCreationError) -> InitError {
+        InitError::FramebufferFailed(err)
+    }
+}
+
+impl From<ImageCreationError> for InitError {
+    fn from(err: ImageCreationError) -> InitError {
+        InitError::ImageFailed(err)
+    }
+}
+
+impl From<GraphicsPipelineCreationError> for InitError {
+    fn from(err: GraphicsPipelineCreationError) -> InitError {
+        InitError::PipelineFailed(err)
+    }
+}
+
+impl From<ComputePipelineCreationError> for InitError {
+    fn from(err: ComputePipelineCreationError) -> InitError {
+        InitError::ComputePipelineFailed(err)
+    }
+}
@@ -0,0 +1,335 @@
+use std::cmp::Ordering;
+
+/// Limbs before the point. Enough for the squares of anything inside a reasonable escape radius.
+const INT_LIMBS: usize = 2;
+
+/// An arbitrary precision fixed point number: a sign and 32 bit limbs, least significant first,
+/// of which all but the last two are after the point.
+///
+/// Only what's needed to iterate a reference orbit is implemented. Both sides of an operation
+/// must have the same precision, results are truncated and anything past 2^64 is lost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedPoint {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl FixedPoint {
+    /// Zero with `frac_limbs` limbs, i.e. `32 * frac_limbs` bits, after the point.
+    pub fn zero(frac_limbs: usize) -> FixedPoint {
+        FixedPoint {
+            negative: false,
+            limbs: vec![0; frac_limbs + INT_LIMBS],
+        }
+    }
+
+    pub fn frac_limbs(&self) -> usize {
+        self.limbs.len() - INT_LIMBS
+    }
+
+    /// `None` if `value` isn't finite or doesn't fit before the point.
+    pub fn from_f64(value: f64, frac_limbs: usize) -> Option<FixedPoint> {
+        if !value.is_finite() || value.abs() >= 2f64.powi(32 * INT_LIMBS as i32) {
+            return None;
+        }
+
+        // Every step only subtracts a power of two multiple, so this is exact.
+        let mut result = FixedPoint::zero(frac_limbs);
+        let mut rest = value.abs();
+        for k in (0 .. result.limbs.len()).rev() {
+            let scale = limb_scale(k, frac_limbs);
+            if rest == 0.0 || scale == 0.0 {
+                break;
+            }
+            let digit = (rest / scale).floor();
+            result.limbs[k] = digit as u32;
+            rest -= digit * scale;
+        }
+        result.negative = value < 0.0 && !result.is_zero();
+        Some(result)
+    }
+
+    /// Parses decimal numbers like `-0.75`, `1.5e-3` or `.25` without going through `f64`, so
+    /// every digit that fits in the precision is kept. `None` if the text isn't a number or the
+    /// number doesn't fit before the point.
+    pub fn parse(text: &str, frac_limbs: usize) -> Option<FixedPoint> {
+        let text = text.trim();
+        let (negative, text) = match text.chars().next() {
+            Some('-') => (true, &text[1..]),
+            Some('+') => (false, &text[1..]),
+            _ => (false, text),
+        };
+        let (mantissa, exponent) = match text.find(|c| c == 'e' || c == 'E') {
+            Some(index) => (&text[.. index], text[index + 1 ..].parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+
+        // The digits as one big integer, times 10^exponent.
+        let mut digits = vec![0u32];
+        let mut seen_digit = false;
+        let mut seen_point = false;
+        let mut exponent = exponent;
+        for c in mantissa.chars() {
+            match c {
+                '.' if !seen_point => seen_point = true,
+                '0' ..= '9' => {
+                    mul_small(&mut digits, 10, c as u32 - '0' as u32);
+                    seen_digit = true;
+                    if seen_point {
+                        exponent = exponent.checked_sub(1)?;
+                    }
+                }
+                _ => return None,
+            }
+        }
+        if !seen_digit {
+            return None;
+        }
+        // Otherwise a huge exponent would be applied one power of ten at a time.
+        if digits.iter().all(|&limb| limb == 0) {
+            return Some(FixedPoint::zero(frac_limbs));
+        }
+
+        // Move the point to the right place, then scale by the decimal exponent.
+        let len = frac_limbs + INT_LIMBS;
+        let mut limbs = vec![0; frac_limbs];
+        limbs.extend(digits);
+        if exponent >= 0 {
+            for _ in 0 .. exponent {
+                mul_small(&mut limbs, 10, 0);
+                if limbs.len() > len && limbs[len ..].iter().any(|&limb| limb != 0) {
+                    return None;
+                }
+            }
+        } else {
+            for _ in 0 .. exponent.checked_neg()? {
+                div_small(&mut limbs, 10);
+                if limbs.iter().all(|&limb| limb == 0) {
+                    break;
+                }
+            }
+        }
+
+        if limbs[len.min(limbs.len()) ..].iter().any(|&limb| limb != 0) {
+            return None;
+        }
+        limbs.resize(len, 0);
+
+        let mut result = FixedPoint { negative: negative, limbs: limbs };
+        result.negative = negative && !result.is_zero();
+        Some(result)
+    }
+
+    /// The nearest `f64`, give or take the rounding of the last bits.
+    pub fn to_f64(&self) -> f64 {
+        let frac_limbs = self.frac_limbs();
+        let magnitude = self.limbs.iter().enumerate().rev()
+            .filter(|&(_, &limb)| limb != 0)
+            .take(3)
+            .map(|(k, &limb)| limb as f64 * limb_scale(k, frac_limbs))
+            .fold(0.0, |sum, value| sum + value);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.iter().all(|&limb| limb == 0)
+    }
+
+    pub fn neg(&self) -> FixedPoint {
+        FixedPoint {
+            negative: !self.negative && !self.is_zero(),
+            limbs: self.limbs.clone(),
+        }
+    }
+
+    pub fn add(&self, other: &FixedPoint) -> FixedPoint {
+        debug_assert_eq!(self.limbs.len(), other.limbs.len());
+        if self.negative == other.negative {
+            return FixedPoint { negative: self.negative, limbs: add_magnitudes(&self.limbs, &other.limbs) };
+        }
+
+        let (negative, limbs) = match compare_magnitudes(&self.limbs, &other.limbs) {
+            Ordering::Less => (other.negative, sub_magnitudes(&other.limbs, &self.limbs)),
+            _ => (self.negative, sub_magnitudes(&self.limbs, &other.limbs)),
+        };
+        let mut result = FixedPoint { negative: negative, limbs: limbs };
+        result.negative = negative && !result.is_zero();
+        result
+    }
+
+    pub fn sub(&self, other: &FixedPoint) -> FixedPoint {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &FixedPoint) -> FixedPoint {
+        debug_assert_eq!(self.limbs.len(), other.limbs.len());
+        let len = self.limbs.len();
+        let mut product = vec![0u32; len * 2];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            if a == 0 {
+                continue;
+            }
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let sum = a as u64 * b as u64 + product[i + j] as u64 + carry;
+                product[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            product[i + len] = carry as u32;
+        }
+
+        // Both factors have frac_limbs after the point, so the product has twice as many.
+        let frac_limbs = self.frac_limbs();
+        let mut result = FixedPoint {
+            negative: self.negative != other.negative,
+            limbs: product[frac_limbs .. frac_limbs + len].to_vec(),
+        };
+        result.negative = result.negative && !result.is_zero();
+        result
+    }
+}
+
+/// The value of a 1 in limb `k`.
+fn limb_scale(k: usize, frac_limbs: usize) -> f64 {
+    2f64.powi(32 * (k as i32 - frac_limbs as i32))
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut carry = 0u64;
+    a.iter().zip(b)
+        .map(|(&a, &b)| {
+            let sum = a as u64 + b as u64 + carry;
+            carry = sum >> 32;
+            sum as u32
+        })
+        .collect()
+}
+
+/// `a - b`, where `a` is at least as large as `b`.
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut borrow = false;
+    a.iter().zip(b)
+        .map(|(&a, &b)| {
+            let (difference, borrow_a) = a.overflowing_sub(b);
+            let (difference, borrow_b) = difference.overflowing_sub(borrow as u32);
+            borrow = borrow_a || borrow_b;
+            difference
+        })
+        .collect()
+}
+
+/// `limbs = limbs * factor + add`, growing as needed.
+fn mul_small(limbs: &mut Vec<u32>, factor: u32, add: u32) {
+    let mut carry = add as u64;
+    for limb in limbs.iter_mut() {
+        let value = *limb as u64 * factor as u64 + carry;
+        *limb = value as u32;
+        carry = value >> 32;
+    }
+    if carry != 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+/// `limbs = limbs / divisor`, truncated.
+fn div_small(limbs: &mut [u32], divisor: u32) {
+    let mut remainder = 0u64;
+    for limb in limbs.iter_mut().rev() {
+        let value = (remainder << 32) | *limb as u64;
+        *limb = (value / divisor as u64) as u32;
+        remainder = value % divisor as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> FixedPoint {
+        FixedPoint::parse(text, 4).unwrap()
+    }
+
+    #[test]
+    fn parses_known_values() {
+        assert_eq!(parse("-0.75").to_f64(), -0.75);
+        assert_eq!(parse("1.5e-3").to_f64(), 1.5e-3);
+        assert_eq!(parse(".25").to_f64(), 0.25);
+        assert_eq!(parse("+3").to_f64(), 3.0);
+        assert_eq!(parse("-1.25E2").to_f64(), -125.0);
+        assert_eq!(parse("0.1").to_f64(), 0.1);
+        assert_eq!(parse("12345678901.5").to_f64(), 12345678901.5);
+        // Truncated, so only within rounding of the nearest double.
+        let tiny = FixedPoint::parse("1e-100", 12).unwrap().to_f64();
+        assert!((tiny - 1e-100).abs() < 1e-114);
+    }
+
+    #[test]
+    fn zero_has_no_sign() {
+        assert_eq!(parse("-0"), FixedPoint::zero(4));
+        assert_eq!(parse("0e2000000000"), FixedPoint::zero(4));
+        assert_eq!(FixedPoint::from_f64(-0.0, 4).unwrap(), FixedPoint::zero(4));
+        assert_eq!(parse("1.5").sub(&parse("1.5")), FixedPoint::zero(4));
+        assert_eq!(parse("-2").mul(&FixedPoint::zero(4)), FixedPoint::zero(4));
+        assert_eq!(FixedPoint::zero(4).neg(), FixedPoint::zero(4));
+    }
+
+    #[test]
+    fn signs() {
+        let (a, b) = (parse("-0.75"), parse("1.5"));
+        assert_eq!(a.add(&b).to_f64(), 0.75);
+        assert_eq!(b.add(&a).to_f64(), 0.75);
+        assert_eq!(a.sub(&b).to_f64(), -2.25);
+        assert_eq!(b.sub(&a).to_f64(), 2.25);
+        assert_eq!(a.mul(&b).to_f64(), -1.125);
+        assert_eq!(a.mul(&a).to_f64(), 0.5625);
+        assert_eq!(a.neg().to_f64(), 0.75);
+    }
+
+    #[test]
+    fn matches_f64_for_small_values() {
+        let values = [0.3, -0.7, 1.0 / 3.0, -2.5, 1e-9, 123.456];
+        for &x in &values {
+            for &y in &values {
+                let (a, b) = (FixedPoint::from_f64(x, 4).unwrap(), FixedPoint::from_f64(y, 4).unwrap());
+                assert!((a.add(&b).to_f64() - (x + y)).abs() <= 1e-15 * (x + y).abs().max(1.0));
+                assert!((a.sub(&b).to_f64() - (x - y)).abs() <= 1e-15 * (x - y).abs().max(1.0));
+                assert!((a.mul(&b).to_f64() - x * y).abs() <= 1e-15 * (x * y).abs().max(1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn orbit_matches_f64() {
+        let c = [-0.743643887, 0.131825904];
+        let (c_re, c_im) = (FixedPoint::from_f64(c[0], 8).unwrap(), FixedPoint::from_f64(c[1], 8).unwrap());
+        let (mut z_re, mut z_im) = (FixedPoint::zero(8), FixedPoint::zero(8));
+        let mut z = [0.0f64, 0.0];
+        for _ in 0 .. 20 {
+            let re = z_re.mul(&z_re).sub(&z_im.mul(&z_im)).add(&c_re);
+            let product = z_re.mul(&z_im);
+            z_im = product.add(&product).add(&c_im);
+            z_re = re;
+            z = [z[0] * z[0] - z[1] * z[1] + c[0], 2.0 * z[0] * z[1] + c[1]];
+        }
+        assert!((z_re.to_f64() - z[0]).abs() < 1e-12);
+        assert!((z_im.to_f64() - z[1]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        assert!(FixedPoint::parse("1e30", 4).is_none());
+        assert!(FixedPoint::parse("-18446744073709551616", 4).is_none());
+        assert!(FixedPoint::parse("abc", 4).is_none());
+        assert!(FixedPoint::parse("1.2.3", 4).is_none());
+        assert!(FixedPoint::parse("", 4).is_none());
+        assert!(FixedPoint::parse("1e", 4).is_none());
+        assert!(FixedPoint::parse("1e-2147483648", 4).is_none());
+        assert!(FixedPoint::parse("0.5e-2147483648", 4).is_none());
+        assert!(FixedPoint::from_f64(1e30, 4).is_none());
+        assert!(FixedPoint::from_f64(std::f64::NAN, 4).is_none());
+    }
+}
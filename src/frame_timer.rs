@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// If set, demos write their frame times to the CSV file at this path on exit.
+pub const CSV_ENV_VAR: &str = "VULKANO_TUTORIAL_FRAME_CSV";
+
+/// Number of recent frames the percentiles are computed over.
+pub const DEFAULT_HISTORY: usize = 240;
+
+/// How strongly the smoothed FPS follows the latest frame. Lower is smoother.
+const FPS_SMOOTHING: f64 = 0.05;
+
+/// Timings of a single frame.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRecord {
+    /// Time since the start of the previous frame.
+    pub delta: Duration,
+    /// Time the CPU spent recording and submitting the frame, without waiting on the GPU.
+    pub cpu: Option<Duration>,
+    /// Time the GPU spent executing the frame, if timestamps are supported.
+    pub gpu: Option<Duration>,
+}
+
+/// A summary of the recent frames.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+    pub fps: f64,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub cpu: Option<Duration>,
+    pub gpu: Option<Duration>,
+}
+
+/// Tracks how long frames take. Call `tick` once per frame, and `record_cpu`/`record_gpu` whenever
+/// those timings become available.
+pub struct FrameTimer {
+    last_tick: Option<Instant>,
+    recent: VecDeque<FrameRecord>,
+    history_len: usize,
+    smoothed_fps: f64,
+    // Every frame since the timer was created, only kept if `keep_all` was set.
+    all: Option<Vec<FrameRecord>>,
+}
+
+impl FrameTimer {
+    /// `keep_all` keeps every frame so it can be written out with `write_csv`.
+    pub fn new(keep_all: bool) -> FrameTimer {
+        FrameTimer {
+            last_tick: None,
+            recent: VecDeque::with_capacity(DEFAULT_HISTORY),
+            history_len: DEFAULT_HISTORY,
+            smoothed_fps: 0.0,
+            all: if keep_all { Some(Vec::new()) } else { None },
+        }
+    }
+
+    /// A timer that keeps every frame if `VULKANO_TUTORIAL_FRAME_CSV` is set, together with that path.
+    pub fn from_env() -> (FrameTimer, Option<PathBuf>) {
+        let path = env::var_os(CSV_ENV_VAR).filter(|path| !path.is_empty()).map(PathBuf::from);
+        (FrameTimer::new(path.is_some()), path)
+    }
+
+    /// Starts a new frame and returns the time since the last one, or zero for the first frame.
+    pub fn tick(&mut self) -> Duration {
+        let now = Instant::now();
+        let delta = match self.last_tick {
+            Some(last_tick) => now - last_tick,
+            None => {
+                self.last_tick = Some(now);
+                return Duration::from_secs(0);
+            }
+        };
+        self.last_tick = Some(now);
+
+        let seconds = duration_secs(delta);
+        if seconds > 0.0 {
+            let fps = 1.0 / seconds;
+            self.smoothed_fps = if self.smoothed_fps == 0.0 {
+                fps
+            } else {
+                self.smoothed_fps + (fps - self.smoothed_fps) * FPS_SMOOTHING
+            };
+        }
+
+        let record = FrameRecord { delta: delta, cpu: None, gpu: None };
+        if self.recent.len() == self.history_len {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(record);
+        if let Some(ref mut all) = self.all {
+            all.push(record);
+        }
+
+        delta
+    }
+
+    /// Attaches the CPU time to the current frame.
+    pub fn record_cpu(&mut self, cpu: Duration) {
+        self.update_last(|record| record.cpu = Some(cpu));
+    }
+
+    /// Attaches the GPU time to the current frame. With frames in flight this is the time of an
+    /// earlier frame, which is close enough for statistics.
+    pub fn record_gpu(&mut self, gpu: Duration) {
+        self.update_last(|record| record.gpu = Some(gpu));
+    }
+
+    fn update_last<F: Fn(&mut FrameRecord)>(&mut self, update: F) {
+        if let Some(record) = self.recent.back_mut() {
+            update(record);
+        }
+        if let Some(record) = self.all.as_mut().and_then(|all| all.last_mut()) {
+            update(record);
+        }
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.smoothed_fps
+    }
+
+    /// The frame time below which `percentile` percent of the recent frames fall.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let mut deltas = self.recent.iter().map(|r| r.delta).collect::<Vec<_>>();
+        if deltas.is_empty() {
+            return Duration::from_secs(0);
+        }
+        deltas.sort();
+        let index = ((percentile / 100.0) * (deltas.len() - 1) as f64).round() as usize;
+        deltas[index.min(deltas.len() - 1)]
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        FrameStats {
+            fps: self.fps(),
+            p50: self.percentile(50.0),
+            p99: self.percentile(99.0),
+            cpu: average(self.recent.iter().filter_map(|r| r.cpu)),
+            gpu: average(self.recent.iter().filter_map(|r| r.gpu)),
+        }
+    }
+
+    /// `base` followed by the current stats, for `Window::set_title`.
+    pub fn title(&self, base: &str) -> String {
+        let stats = self.stats();
+        let mut title = format!("{} - {:.0} fps, p50 {:.2} ms, p99 {:.2} ms", base, stats.fps,
+                                duration_ms(stats.p50), duration_ms(stats.p99));
+        if let Some(cpu) = stats.cpu {
+            title += &format!(", cpu {:.2} ms", duration_ms(cpu));
+        }
+        if let Some(gpu) = stats.gpu {
+            title += &format!(", gpu {:.2} ms", duration_ms(gpu));
+        }
+        title
+    }
+
+    /// Writes every frame as `frame,delta_ms,cpu_ms,gpu_ms`. Does nothing unless the timer was created
+    /// with `keep_all`.
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let all = match self.all {
+            Some(ref all) => all,
+            None => return Ok(()),
+        };
+
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "frame,delta_ms,cpu_ms,gpu_ms")?;
+        for (i, record) in all.iter().enumerate() {
+            let cpu = record.cpu.map(|d| format!("{:.4}", duration_ms(d))).unwrap_or_default();
+            let gpu = record.gpu.map(|d| format!("{:.4}", duration_ms(d))).unwrap_or_default();
+            writeln!(file, "{},{:.4},{},{}", i, duration_ms(record.delta), cpu, gpu)?;
+        }
+        file.flush()
+    }
+}
+
+fn average<I: Iterator<Item = Duration>>(durations: I) -> Option<Duration> {
+    let (sum, count) = durations.fold((Duration::from_secs(0), 0), |(sum, count), d| (sum + d, count + 1));
+    if count == 0 { None } else { Some(sum / count) }
+}
+
+fn duration_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
+
+pub fn duration_ms(duration: Duration) -> f64 {
+    duration_secs(duration) * 1000.0
+}
@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+use winit::{DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+            VirtualKeyCode, WindowEvent};
+use winit::dpi::LogicalPosition;
+
+/// Pixel scroll deltas (touchpads) are divided by this to get roughly the same units as a mouse wheel.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Keyboard and mouse state, built up from the events of a frame.
+///
+/// Call `begin_frame` before polling events and pass every event to `handle_event`. Afterwards the
+/// `was_*` queries tell what happened during this frame, the `is_*` queries what is currently held.
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    keys_held: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    keys_released: HashSet<VirtualKeyCode>,
+    buttons_held: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    cursor_position: Option<LogicalPosition>,
+    cursor_delta: (f64, f64),
+    scroll_delta: (f64, f64),
+    modifiers: ModifiersState,
+    close_requested: bool,
+    resized: bool,
+    hidpi_factor_changed: Option<f64>,
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    /// Forgets everything that only applies to the previous frame.
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.cursor_delta = (0.0, 0.0);
+        self.scroll_delta = (0.0, 0.0);
+        self.close_requested = false;
+        self.resized = false;
+        self.hidpi_factor_changed = None;
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::WindowEvent { event, .. } => self.handle_window_event(event),
+            // Raw motion keeps working when the cursor hits the edge of the screen.
+            Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => {
+                self.cursor_delta.0 += delta.0;
+                self.cursor_delta.1 += delta.1;
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::CloseRequested => self.close_requested = true,
+            WindowEvent::Resized(_) => self.resized = true,
+            WindowEvent::HiDpiFactorChanged(factor) => self.hidpi_factor_changed = Some(factor),
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode, modifiers, .. }, .. } => {
+                self.modifiers = modifiers;
+                if let Some(key) = virtual_keycode {
+                    match state {
+                        // Key repeat sends more presses while the key is held, don't count those.
+                        ElementState::Pressed => if self.keys_held.insert(key) {
+                            self.keys_pressed.insert(key);
+                        },
+                        ElementState::Released => if self.keys_held.remove(&key) {
+                            self.keys_released.insert(key);
+                        },
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, modifiers, .. } => {
+                self.modifiers = modifiers;
+                match state {
+                    ElementState::Pressed => if self.buttons_held.insert(button) {
+                        self.buttons_pressed.insert(button);
+                    },
+                    ElementState::Released => if self.buttons_held.remove(&button) {
+                        self.buttons_released.insert(button);
+                    },
+                }
+            }
+            WindowEvent::CursorMoved { position, modifiers, .. } => {
+                self.modifiers = modifiers;
+                self.cursor_position = Some(position);
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, modifiers, .. } => {
+                self.modifiers = modifiers;
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x as f64, y as f64),
+                    MouseScrollDelta::PixelDelta(position) => (position.x / PIXELS_PER_LINE, position.y / PIXELS_PER_LINE),
+                };
+                self.scroll_delta.0 += x;
+                self.scroll_delta.1 += y;
+            }
+            // Release events for keys held while the window loses focus never arrive.
+            WindowEvent::Focused(false) => {
+                self.keys_released.extend(self.keys_held.drain());
+                self.buttons_released.extend(self.buttons_held.drain());
+            }
+            _ => (),
+        }
+    }
+
+    pub fn is_key_held(&self, key: VirtualKeyCode) -> bool {
+        self.keys_held.contains(&key)
+    }
+
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn was_key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_button_held(&self, button: MouseButton) -> bool {
+        self.buttons_held.contains(&button)
+    }
+
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Where the cursor is inside the window in logical pixels, `None` if it's outside. Use
+    /// `VulkanWindow::to_physical` to get swapchain pixels.
+    pub fn cursor_position(&self) -> Option<LogicalPosition> {
+        self.cursor_position
+    }
+
+    /// How far the mouse moved this frame, in unscaled device units.
+    pub fn cursor_delta(&self) -> (f64, f64) {
+        self.cursor_delta
+    }
+
+    /// How far the wheel was scrolled this frame, in lines.
+    pub fn scroll_delta(&self) -> (f64, f64) {
+        self.scroll_delta
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    pub fn resized(&self) -> bool {
+        self.resized
+    }
+
+    /// The new scale factor, if the window moved to a monitor with a different one this frame.
+    pub fn hidpi_factor_changed(&self) -> Option<f64> {
+        self.hidpi_factor_changed
+    }
+}
+
+/// Things the demos can do in response to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ToggleFullscreen,
+    Screenshot,
+    ToggleVsync,
+}
+
+/// Which key triggers which `Action`.
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(VirtualKeyCode, Action)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            bindings: vec![
+                (VirtualKeyCode::Escape, Action::Quit),
+                (VirtualKeyCode::F11, Action::ToggleFullscreen),
+                (VirtualKeyCode::F12, Action::Screenshot),
+                (VirtualKeyCode::V, Action::ToggleVsync),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// No bindings at all.
+    pub fn empty() -> KeyBindings {
+        KeyBindings { bindings: Vec::new() }
+    }
+
+    /// Makes `key` trigger `action`, replacing whatever it was bound to before.
+    pub fn bind(&mut self, key: VirtualKeyCode, action: Action) {
+        self.unbind(key);
+        self.bindings.push((key, action));
+    }
+
+    pub fn unbind(&mut self, key: VirtualKeyCode) {
+        self.bindings.retain(|&(k, _)| k != key);
+    }
+
+    /// The actions whose keys were pressed this frame.
+    pub fn triggered(&self, input: &InputState) -> Vec<Action> {
+        self.bindings.iter()
+            .filter(|&&(key, _)| input.was_key_pressed(key))
+            .map(|&(_, action)| action)
+            .collect()
+    }
+}
@@ -0,0 +1,28 @@
+pub mod debug;
+pub mod device_selection;
+pub mod error;
+pub mod fixed_point;
+pub mod frame_timer;
+pub mod input;
+pub mod mandelbrot;
+pub mod palette;
+pub mod profiler;
+pub mod renderer;
+pub mod timestamps;
+pub mod vulkan_init;
+pub mod vulkan_window;
+pub mod window_config;
+
+pub use device_selection::{DeviceSelection, DeviceRequirements};
+pub use error::InitError;
+pub use fixed_point::FixedPoint;
+pub use frame_timer::{FrameTimer, FrameStats};
+pub use input::{InputState, KeyBindings, Action};
+pub use mandelbrot::{MandelbrotParams, MandelbrotPipeline, FractalKind, Precision, ReferenceOrbit, MandelbrotError,
+                     MAX_MULTIBROT_POWER};
+pub use palette::{Palette, PaletteError};
+pub use profiler::{Profiler, ScopeTiming, ProfileError};
+pub use renderer::{Renderer, FrameStatus, DEFAULT_FRAMES_IN_FLIGHT};
+pub use vulkan_init::{VulkanInit, VulkanInitBuilder, InstanceConfig, Presentation, create_instance};
+pub use vulkan_window::{VulkanWindow, SurfaceFormat, SwapchainStatus};
+pub use window_config::{WindowConfig, WindowMode, SurfaceFormatPreference, DepthBuffer};
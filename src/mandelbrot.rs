@@ -1,22 +1,23 @@
+use std::fmt;
 use std::sync::Arc;
-use vulkano::instance::Instance;
-use vulkano::instance::InstanceExtensions;
-use vulkano::instance::PhysicalDevice;
-use vulkano::device::Device;
-use vulkano::device::DeviceExtensions;
-use vulkano::device::Features;
+use std::error::Error;
 use vulkano::buffer::BufferUsage;
 use vulkano::buffer::CpuAccessibleBuffer;
 use vulkano::command_buffer::AutoCommandBufferBuilder;
-use vulkano::command_buffer::CommandBuffer;
-use vulkano::sync::GpuFuture;
-use vulkano::pipeline::ComputePipeline;
 use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::PipelineLayout;
+use vulkano::device::Device;
 use vulkano::format::Format;
-use vulkano::image::Dimensions;
+use vulkano::image::ImageAccess;
 use vulkano::image::StorageImage;
-use vulkano::format::ClearValue;
-use image::{ImageBuffer, Rgba};
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::ComputePipeline;
+
+use crate::error::InitError;
+use crate::fixed_point::FixedPoint;
+
+/// Width of the shader's work groups in both directions.
+pub const LOCAL_SIZE: u32 = 8;
 
 mod cs {
     vulkano_shaders::shader!{
@@ -27,137 +28,704 @@ mod cs {
 
 layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
 
+// 0: Mandelbrot, 1: Julia, 2: Burning Ship, 3: Tricorn, 4: Multibrot. See `FractalKind::index`.
+layout(constant_id = 0) const uint kind = 0;
+
 layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
 
+layout(set = 0, binding = 1) readonly buffer Palette {
+    vec4 colors[];
+} palette;
+
+layout(push_constant) uniform Params {
+    vec2 center;
+    vec2 julia_c;
+    float zoom;
+    float aspect_ratio;
+    uint max_iterations;
+    float escape_radius;
+    float palette_offset;
+    float palette_density;
+    uint smooth_coloring;
+    float power;
+} params;
+
+vec2 square(vec2 z) {
+    return vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y);
+}
+
+// One step of the orbit, z -> f(z) + c.
+vec2 iterate(vec2 z, vec2 c) {
+    if (kind == 2) {
+        z = abs(z);
+    } else if (kind == 3) {
+        z.y = -z.y;
+    }
+
+    if (kind == 4) {
+        // atan(0, 0) is undefined, and the orbit starts at 0.
+        if (z == vec2(0.0)) {
+            return c;
+        }
+        float r = pow(length(z), params.power);
+        float theta = atan(z.y, z.x) * params.power;
+        return r * vec2(cos(theta), sin(theta)) + c;
+    }
+    return square(z) + c;
+}
+
+// Interpolates between the palette's stops, wrapping around so the gradient can be cycled.
+vec3 palette_color(float t) {
+    uint n = uint(palette.colors.length());
+    float x = fract(t) * float(n);
+    uint i = uint(x) % n;
+    return mix(palette.colors[i].rgb, palette.colors[(i + 1) % n].rgb, fract(x));
+}
+
 void main() {
+    // The image doesn't have to be a multiple of the work group size.
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(imageSize(img))))) {
+        return;
+    }
+
     vec2 norm_coordinates = (gl_GlobalInvocationID.xy + vec2(0.5)) / vec2(imageSize(img));
-    vec2 c = (norm_coordinates - vec2(0.5)) * 3.0 - vec2(0.5, 0.0);
+    // At zoom 1 the view is 3 units high.
+    vec2 point = params.center + (norm_coordinates - vec2(0.5)) * vec2(params.aspect_ratio, 1.0) * 3.0 / params.zoom;
+
+    // Julia sets start the orbit at the pixel with a fixed c, everything else starts at 0 with c at the pixel.
+    vec2 z = kind == 1 ? point : vec2(0.0, 0.0);
+    vec2 c = kind == 1 ? params.julia_c : point;
+    float radius_squared = params.escape_radius * params.escape_radius;
+    uint i;
+    for (i = 0; i < params.max_iterations; i++) {
+        z = iterate(z, c);
+
+        if (dot(z, z) > radius_squared) {
+            break;
+        }
+    }
+
+    vec4 to_write = vec4(0.0, 0.0, 0.0, 1.0);
+    if (i < params.max_iterations) {
+        float mu = float(i);
+        if (params.smooth_coloring != 0) {
+            // Normalized iteration count, removes the bands between whole iteration counts.
+            float power = kind == 4 ? params.power : 2.0;
+            mu += 1.0 - log(log(length(z))) / log(power);
+        }
+        float t = mu / float(params.max_iterations) * params.palette_density + params.palette_offset;
+        to_write = vec4(palette_color(t), 1.0);
+    }
+    imageStore(img, ivec2(gl_GlobalInvocationID.xy), to_write);
+}
+"
+    }
+}
+
+mod cs64 {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: "
+
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+// Same kinds as the single precision shader.
+layout(constant_id = 0) const uint kind = 0;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+layout(set = 0, binding = 1) readonly buffer Palette {
+    vec4 colors[];
+} palette;
+
+layout(push_constant) uniform Params {
+    dvec2 center;
+    dvec2 julia_c;
+    double zoom;
+    float aspect_ratio;
+    uint max_iterations;
+    float escape_radius;
+    float palette_offset;
+    float palette_density;
+    uint smooth_coloring;
+    float power;
+} params;
+
+dvec2 mul(dvec2 a, dvec2 b) {
+    return dvec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// There are no double precision pow, atan or cos, so Multibrot powers are done by repeated
+// multiplication. Fractional powers are rejected before dispatching.
+uint whole_power() {
+    return max(uint(round(params.power)), 2u);
+}
+
+dvec2 iterate(dvec2 z, dvec2 c) {
+    if (kind == 2) {
+        z = abs(z);
+    } else if (kind == 3) {
+        z.y = -z.y;
+    }
+
+    if (kind == 4) {
+        dvec2 result = z;
+        for (uint k = 1; k < whole_power(); k++) {
+            result = mul(result, z);
+        }
+        return result + c;
+    }
+    return mul(z, z) + c;
+}
+
+vec3 palette_color(float t) {
+    uint n = uint(palette.colors.length());
+    float x = fract(t) * float(n);
+    uint i = uint(x) % n;
+    return mix(palette.colors[i].rgb, palette.colors[(i + 1) % n].rgb, fract(x));
+}
+
+void main() {
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(imageSize(img))))) {
+        return;
+    }
+
+    dvec2 norm_coordinates = (dvec2(gl_GlobalInvocationID.xy) + dvec2(0.5)) / dvec2(imageSize(img));
+    dvec2 point = params.center + (norm_coordinates - dvec2(0.5)) * dvec2(params.aspect_ratio, 1.0) * 3.0 / params.zoom;
+
+    dvec2 z = kind == 1 ? point : dvec2(0.0, 0.0);
+    dvec2 c = kind == 1 ? params.julia_c : point;
+    double radius_squared = double(params.escape_radius) * double(params.escape_radius);
+    uint i;
+    for (i = 0; i < params.max_iterations; i++) {
+        z = iterate(z, c);
+
+        if (dot(z, z) > radius_squared) {
+            break;
+        }
+    }
+
+    vec4 to_write = vec4(0.0, 0.0, 0.0, 1.0);
+    if (i < params.max_iterations) {
+        float mu = float(i);
+        if (params.smooth_coloring != 0) {
+            // The orbit has escaped, so single precision is plenty for the coloring.
+            float power = kind == 4 ? float(whole_power()) : 2.0;
+            mu += 1.0 - log(log(length(vec2(z)))) / log(power);
+        }
+        float t = mu / float(params.max_iterations) * params.palette_density + params.palette_offset;
+        to_write = vec4(palette_color(t), 1.0);
+    }
+    imageStore(img, ivec2(gl_GlobalInvocationID.xy), to_write);
+}
+"
+    }
+}
+
+mod perturbation_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: "
+
+#version 450
 
-    vec2 z = vec2(0.0, 0.0);
-    float i;
-    for (i = 0.0; i < 1.0; i += 0.05) {
-        z = vec2(
-            z.x * z.x - z.y * z.y + c.x,
-            z.y * z.x + z.x * z.y + c.y
-        );
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+layout(set = 0, binding = 1) readonly buffer Palette {
+    vec4 colors[];
+} palette;
+
+// The orbit of the image's center, computed on the CPU in arbitrary precision. Starts at 0 and
+// has at least two points.
+layout(set = 0, binding = 2) readonly buffer Orbit {
+    dvec2 points[];
+} orbit;
+
+layout(push_constant) uniform Params {
+    double zoom;
+    float aspect_ratio;
+    uint max_iterations;
+    float escape_radius;
+    float palette_offset;
+    float palette_density;
+    uint smooth_coloring;
+} params;
+
+dvec2 mul(dvec2 a, dvec2 b) {
+    return dvec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+vec3 palette_color(float t) {
+    uint n = uint(palette.colors.length());
+    float x = fract(t) * float(n);
+    uint i = uint(x) % n;
+    return mix(palette.colors[i].rgb, palette.colors[(i + 1) % n].rgb, fract(x));
+}
+
+void main() {
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(imageSize(img))))) {
+        return;
+    }
 
-        if (length(z) > 4.0) {
+    // Only the offset from the center is needed, which stays within double range far past the
+    // point where the coordinates themselves would round to the center.
+    dvec2 norm_coordinates = (dvec2(gl_GlobalInvocationID.xy) + dvec2(0.5)) / dvec2(imageSize(img));
+    dvec2 dc = (norm_coordinates - dvec2(0.5)) * dvec2(params.aspect_ratio, 1.0) * 3.0 / params.zoom;
+
+    uint reference_length = uint(orbit.points.length());
+    uint m = 0;
+    dvec2 dz = dvec2(0.0, 0.0);
+    dvec2 z = dvec2(0.0, 0.0);
+    double radius_squared = double(params.escape_radius) * double(params.escape_radius);
+    uint i;
+    for (i = 0; i < params.max_iterations; i++) {
+        // With z = Z + dz and c = C + dc: z² + c = Z² + C + 2 Z dz + dz² + dc.
+        dz = 2.0 * mul(orbit.points[m], dz) + mul(dz, dz) + dc;
+        m++;
+        z = orbit.points[m] + dz;
+
+        if (dot(z, z) > radius_squared) {
             break;
         }
+
+        // Continue from the start of the reference when z gets closer to 0 than the offset, where
+        // the offset would lose its precision, or when the reference has escaped.
+        if (dot(z, z) < dot(dz, dz) || m == reference_length - 1) {
+            dz = z;
+            m = 0;
+        }
     }
 
-    vec4 to_write = vec4(i, 0.3 - i, 0.0, 1.0);
+    vec4 to_write = vec4(0.0, 0.0, 0.0, 1.0);
+    if (i < params.max_iterations) {
+        float mu = float(i);
+        if (params.smooth_coloring != 0) {
+            mu += 1.0 - log2(log(length(vec2(z))));
+        }
+        float t = mu / float(params.max_iterations) * params.palette_density + params.palette_offset;
+        to_write = vec4(palette_color(t), 1.0);
+    }
     imageStore(img, ivec2(gl_GlobalInvocationID.xy), to_write);
 }
 "
     }
 }
 
-fn main() {
-    let instance = Instance::new(None, &InstanceExtensions::none(), None)
-        .expect("Failed to create instance.");
+/// The highest Multibrot power. The double precision shader does one multiplication per power.
+pub const MAX_MULTIBROT_POWER: f32 = 64.0;
+
+/// The escape-time formula to iterate. Each kind gets its own specialized pipeline, the values
+/// inside are passed as push constants.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FractalKind {
+    /// z² + c, starting at z = 0 with c at the pixel.
+    Mandelbrot,
+    /// z² + c, starting at the pixel with this fixed c.
+    Julia { c: [f64; 2] },
+    /// Mandelbrot with the absolute values of z's components before squaring.
+    BurningShip,
+    /// Mandelbrot with the complex conjugate of z before squaring.
+    Tricorn,
+    /// z^power + c, for powers above 1 up to `MAX_MULTIBROT_POWER`. Others are clamped into that range.
+    Multibrot { power: f32 },
+}
+
+impl FractalKind {
+    pub const NAMES: &'static [&'static str] = &["mandelbrot", "julia", "burning-ship", "tricorn", "multibrot"];
+    const COUNT: u32 = 5;
+
+    /// The kind called `name`, with a well known Julia constant and power 3 for Multibrot.
+    pub fn from_name(name: &str) -> Option<FractalKind> {
+        match name.to_lowercase().as_str() {
+            "mandelbrot" => Some(FractalKind::Mandelbrot),
+            "julia" => Some(FractalKind::Julia { c: [-0.8, 0.156] }),
+            "burning-ship" | "burningship" => Some(FractalKind::BurningShip),
+            "tricorn" => Some(FractalKind::Tricorn),
+            "multibrot" => Some(FractalKind::Multibrot { power: 3.0 }),
+            _ => None,
+        }
+    }
+
+    /// Roughly the middle of the interesting part, for a view at zoom 1.
+    pub fn default_center(&self) -> [f64; 2] {
+        match self {
+            FractalKind::Mandelbrot => [-0.5, 0.0],
+            FractalKind::Julia { .. } => [0.0, 0.0],
+            FractalKind::BurningShip => [-0.4, -0.5],
+            FractalKind::Tricorn => [-0.3, 0.0],
+            FractalKind::Multibrot { .. } => [0.0, 0.0],
+        }
+    }
+
+    /// The value of the shader's `kind` specialization constant.
+    fn index(&self) -> u32 {
+        match self {
+            FractalKind::Mandelbrot => 0,
+            FractalKind::Julia { .. } => 1,
+            FractalKind::BurningShip => 2,
+            FractalKind::Tricorn => 3,
+            FractalKind::Multibrot { .. } => 4,
+        }
+    }
+}
+
+/// How the shaders represent coordinates. Each one zooms deeper than the one before, but is slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// 32 bit floats, pixelated past a zoom of about 1e4.
+    Single,
+    /// 64 bit floats, good to a zoom of about 1e12. Needs `shader_float64`, and Multibrot sets
+    /// need a whole power.
+    Double,
+    /// Offsets in 64 bit floats from a `ReferenceOrbit` that was computed in arbitrary precision,
+    /// good to a zoom of about 1e300. Needs `shader_float64` and only renders the Mandelbrot set.
+    Perturbation,
+}
 
-    let physical = PhysicalDevice::enumerate(&instance).next().expect("No physical devices available.");
+impl Precision {
+    pub const NAMES: &'static [&'static str] = &["single", "double", "perturbation"];
 
-    for family in physical.queue_families() {
-        println!("Found a queue family with {:?} queues", family.queues_count());
+    pub fn from_name(name: &str) -> Option<Precision> {
+        match name.to_lowercase().as_str() {
+            "single" => Some(Precision::Single),
+            "double" => Some(Precision::Double),
+            "perturbation" => Some(Precision::Perturbation),
+            _ => None,
+        }
     }
 
-    let queue_family =   physical.queue_families()
-        .find(|&q| q.supports_graphics())
-        .expect("Couldn't find a graphical queue family.");
+    /// Roughly the most pixels per unit of the complex plane (see `MandelbrotParams::pixels_per_unit`)
+    /// at which neighbouring pixels still get different coordinates.
+    pub fn max_pixels_per_unit(&self) -> f64 {
+        match self {
+            Precision::Single => 1e5,
+            Precision::Double => 1e12,
+            Precision::Perturbation => 1e300,
+        }
+    }
+}
 
-    let (device, mut queues) = {
-        Device::new(physical, &Features::none(), &DeviceExtensions::none(), 
-                    [(queue_family, 0.5)].iter().cloned())
-            .expect("Failed to create device.")
-    };
+#[derive(Debug)]
+pub enum MandelbrotError {
+    /// Double precision and perturbation need a device created with `shader_float64` enabled.
+    Float64Unsupported,
+    /// Perturbation only knows the Mandelbrot formula.
+    PerturbationUnsupported(FractalKind),
+    /// Double precision only renders Multibrot sets with whole powers.
+    FractionalPower(f32),
+}
+
+impl fmt::Display for MandelbrotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MandelbrotError::Float64Unsupported => write!(f, "The device doesn't have shader_float64 enabled."),
+            MandelbrotError::PerturbationUnsupported(kind) => write!(f, "Perturbation can't render {:?}.", kind),
+            MandelbrotError::FractionalPower(power) => {
+                write!(f, "Double precision can only render whole Multibrot powers, not {}.", power)
+            }
+        }
+    }
+}
+
+impl Error for MandelbrotError {}
+
+/// Which part of the set to render and in how much detail.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MandelbrotParams {
+    pub kind: FractalKind,
+    /// The point of the complex plane in the middle of the image. `Precision::Perturbation` takes
+    /// the exact center from its `ReferenceOrbit` instead.
+    pub center: [f64; 2],
+    /// Magnification. At 1 the image is 3 units high.
+    pub zoom: f64,
+    pub max_iterations: u32,
+    /// Points whose orbit gets further than this from the origin are outside the set. Larger radii
+    /// make smooth coloring more accurate.
+    pub escape_radius: f32,
+    /// Width divided by height of the image, so pixels stay square.
+    pub aspect_ratio: f32,
+    /// Shifts the palette, 1 is a full cycle. Animate it to cycle the colors.
+    pub palette_offset: f32,
+    /// How many times the palette repeats between 0 and `max_iterations` iterations.
+    pub palette_density: f32,
+    /// Color by normalized iteration count instead of whole iterations, which avoids banding.
+    pub smooth_coloring: bool,
+}
 
-    let queue = queues.next().unwrap();
+impl Default for MandelbrotParams {
+    fn default() -> MandelbrotParams {
+        MandelbrotParams {
+            kind: FractalKind::Mandelbrot,
+            center: [-0.5, 0.0],
+            zoom: 1.0,
+            max_iterations: 256,
+            escape_radius: 256.0,
+            aspect_ratio: 1.0,
+            palette_offset: 0.0,
+            palette_density: 1.0,
+            smooth_coloring: true,
+        }
+    }
+}
 
-    /*
-    let source_content = 0..64;
-    let source = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), source_content)
-        .expect("Failed to create source buffer.");
+impl MandelbrotParams {
+    /// The aspect ratio for an image of this size.
+    pub fn with_size(self, width: u32, height: u32) -> MandelbrotParams {
+        MandelbrotParams {
+            aspect_ratio: width as f32 / height as f32,
+            .. self
+        }
+    }
 
-    let dest_content = (0..64).map(|_| 0);
-    let dest = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), dest_content)
-        .expect("Failed to created destination buffer.");
+    /// Pixels per unit of the complex plane, for an image `height` pixels high.
+    pub fn pixels_per_unit(&self, height: u32) -> f64 {
+        self.zoom * height as f64 / 3.0
+    }
 
-    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
-        .copy_buffer(source.clone(), dest.clone()).unwrap()
-        .build().unwrap();
+    fn julia_c(&self) -> [f64; 2] {
+        match self.kind {
+            FractalKind::Julia { c } => c,
+            _ => [0.0, 0.0],
+        }
+    }
 
-    let finished = command_buffer.execute(queue.clone()).unwrap();
-    finished.then_signal_fence_and_flush().unwrap()
-        .wait(None).unwrap();
+    fn power(&self) -> f32 {
+        match self.kind {
+            // Smooth coloring divides by log(power), and the double precision shader loops power times.
+            FractalKind::Multibrot { power } if power.is_finite() => {
+                power.max(1.0 + std::f32::EPSILON).min(MAX_MULTIBROT_POWER)
+            }
+            _ => 2.0,
+        }
+    }
 
-    let src_content = source.read().unwrap();
-    let dest_content = dest.read().unwrap();
+    fn push_constants(&self) -> cs::ty::Params {
+        let julia_c = self.julia_c();
+
+        cs::ty::Params {
+            center: [self.center[0] as f32, self.center[1] as f32],
+            julia_c: [julia_c[0] as f32, julia_c[1] as f32],
+            zoom: self.zoom as f32,
+            aspect_ratio: self.aspect_ratio,
+            max_iterations: self.max_iterations.max(1),
+            escape_radius: self.escape_radius,
+            palette_offset: self.palette_offset,
+            palette_density: self.palette_density,
+            smooth_coloring: self.smooth_coloring as u32,
+            power: self.power(),
+        }
+    }
 
-    assert_eq!(&*src_content, &*dest_content)
-    */
+    fn double_push_constants(&self) -> cs64::ty::Params {
+        cs64::ty::Params {
+            center: self.center,
+            julia_c: self.julia_c(),
+            zoom: self.zoom,
+            aspect_ratio: self.aspect_ratio,
+            max_iterations: self.max_iterations.max(1),
+            escape_radius: self.escape_radius,
+            palette_offset: self.palette_offset,
+            palette_density: self.palette_density,
+            smooth_coloring: self.smooth_coloring as u32,
+            power: self.power(),
+        }
+    }
 
-    /*
-    let data_iter = 0..65536;
-    let data_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), data_iter).unwrap();
+    fn perturbation_push_constants(&self) -> perturbation_cs::ty::Params {
+        perturbation_cs::ty::Params {
+            zoom: self.zoom,
+            aspect_ratio: self.aspect_ratio,
+            max_iterations: self.max_iterations.max(1),
+            escape_radius: self.escape_radius,
+            palette_offset: self.palette_offset,
+            palette_density: self.palette_density,
+            smooth_coloring: self.smooth_coloring as u32,
+        }
+    }
+}
 
-    let shader = cs::Shader::load(device.clone()).unwrap();
-    let compute_pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
-                                    .expect("Failed to create compute pipeline"));
+/// The orbit of the image's center for `Precision::Perturbation`, iterated in arbitrary precision
+/// and rounded to doubles afterwards.
+#[derive(Debug, Clone)]
+pub struct ReferenceOrbit {
+    /// z₀ = 0 up to the first point that escaped, or `max_iterations + 1` points.
+    pub points: Vec<[f64; 2]>,
+}
 
-    let set = Arc::new(PersistentDescriptorSet::start(compute_pipeline.clone(), 0)
-                       .add_buffer(data_buffer.clone()).unwrap()
-                       .build().unwrap()
-                      );
+impl ReferenceOrbit {
+    /// How many fractional limbs a `FixedPoint` center needs to be placed within a fraction of a
+    /// pixel, with some bits to spare for the error that builds up while iterating.
+    pub fn frac_limbs_for(params: &MandelbrotParams, height: u32) -> usize {
+        let bits = params.pixels_per_unit(height).log2().max(0.0) + 64.0;
+        (bits / 32.0).ceil() as usize
+    }
 
-    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
-        .dispatch([1024, 1, 1], compute_pipeline.clone(), set.clone(), ()).unwrap()
-        .build().unwrap();
+    pub fn compute(center: &[FixedPoint; 2], params: &MandelbrotParams) -> ReferenceOrbit {
+        let (c_re, c_im) = (&center[0], &center[1]);
+        let mut z_re = FixedPoint::zero(c_re.frac_limbs());
+        let mut z_im = FixedPoint::zero(c_re.frac_limbs());
+        // Keeps |z|² well below the 2^64 a FixedPoint can hold.
+        let radius = params.escape_radius.min(65536.0) as f64;
+
+        let mut points = vec![[0.0, 0.0]];
+        for _ in 0 .. params.max_iterations.max(1) {
+            let re = z_re.mul(&z_re).sub(&z_im.mul(&z_im)).add(c_re);
+            let product = z_re.mul(&z_im);
+            z_im = product.add(&product).add(c_im);
+            z_re = re;
+
+            let point = [z_re.to_f64(), z_im.to_f64()];
+            points.push(point);
+            if point[0] * point[0] + point[1] * point[1] > radius * radius {
+                break;
+            }
+        }
 
-    let finished = command_buffer.execute(queue.clone()).unwrap();
-    finished.then_signal_fence_and_flush().unwrap()
-        .wait(None).unwrap();
+        ReferenceOrbit { points: points }
+    }
 
-    let content = data_buffer.read().unwrap();
-    for (n, val) in content.iter().enumerate() {
-        assert_eq!(*val, n as u32 * 12);
+    /// Uploads the points for the perturbation shader to read as `dvec2 points[]`.
+    pub fn upload(&self, device: &Arc<Device>) -> Result<Arc<CpuAccessibleBuffer<[[f64; 2]]>>, DeviceMemoryAllocError> {
+        CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::storage_buffer(), self.points.iter().cloned())
     }
+}
 
-    println!("Success!")
-    */
+/// The compiled compute pipelines, one per `FractalKind` and precision. Build them once and
+/// dispatch them with different `MandelbrotParams`.
+pub struct MandelbrotPipeline {
+    pipelines: Vec<Arc<ComputePipeline<PipelineLayout<cs::Layout>>>>,
+    // Both empty unless the device has shader_float64 enabled.
+    double_pipelines: Vec<Arc<ComputePipeline<PipelineLayout<cs64::Layout>>>>,
+    perturbation_pipeline: Option<Arc<ComputePipeline<PipelineLayout<perturbation_cs::Layout>>>>,
+}
 
-    let image = StorageImage::new(device.clone(), 
-                                  Dimensions::Dim2d{ width: 1024, height: 1024 },
-                                  Format::R8G8B8A8Unorm,
-                                  Some(queue.family())).unwrap();
+impl MandelbrotPipeline {
+    pub fn new(device: &Arc<Device>) -> Result<MandelbrotPipeline, InitError> {
+        let shader = cs::Shader::load(device.clone()).map_err(InitError::ShaderLoadFailed)?;
 
+        let mut pipelines = Vec::new();
+        for kind in 0 .. FractalKind::COUNT {
+            let constants = cs::SpecializationConstants { kind: kind };
+            pipelines.push(Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &constants)?));
+        }
 
-    let shader = cs::Shader::load(device.clone()).unwrap();
-    let compute_pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
-                                    .expect("Failed to create compute pipeline"));
+        // Loading a shader that uses doubles panics if the feature isn't enabled.
+        let mut double_pipelines = Vec::new();
+        let mut perturbation_pipeline = None;
+        if device.enabled_features().shader_float64 {
+            let shader = cs64::Shader::load(device.clone()).map_err(InitError::ShaderLoadFailed)?;
+            for kind in 0 .. FractalKind::COUNT {
+                let constants = cs64::SpecializationConstants { kind: kind };
+                double_pipelines.push(Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &constants)?));
+            }
+
+            let shader = perturbation_cs::Shader::load(device.clone()).map_err(InitError::ShaderLoadFailed)?;
+            perturbation_pipeline = Some(Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())?));
+        }
 
-    let set = Arc::new(PersistentDescriptorSet::start(compute_pipeline.clone(), 0)
-                       .add_image(image.clone()).unwrap()
-                       .build().unwrap()
-                      );
+        Ok(MandelbrotPipeline {
+            pipelines: pipelines,
+            double_pipelines: double_pipelines,
+            perturbation_pipeline: perturbation_pipeline,
+        })
+    }
 
-    let buf = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), 
-                                             (0 .. 1024 * 1024 * 4).map(|_| 0u8))
-        .expect("Failed to create CpuAccessibleBuffer");
+    pub fn supports(&self, precision: Precision, kind: FractalKind) -> bool {
+        match precision {
+            Precision::Single => true,
+            Precision::Double => !self.double_pipelines.is_empty() && has_whole_power(kind),
+            Precision::Perturbation => self.perturbation_pipeline.is_some() && kind == FractalKind::Mandelbrot,
+        }
+    }
 
-    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
-        .dispatch([1024/8, 1024/8, 1], compute_pipeline.clone(), set.clone(), ()).unwrap()
-        .copy_image_to_buffer(image.clone(), buf.clone()).unwrap()
-        .build().unwrap();
+    /// The fastest supported precision that can still tell the pixels of an image `height` pixels
+    /// high apart, or the most precise one there is. That one may still not be enough, compare
+    /// against `Precision::max_pixels_per_unit`.
+    pub fn precision_for(&self, params: &MandelbrotParams, height: u32) -> Precision {
+        let pixels_per_unit = params.pixels_per_unit(height);
+        if pixels_per_unit >= Precision::Double.max_pixels_per_unit() && self.supports(Precision::Perturbation, params.kind) {
+            Precision::Perturbation
+        } else if pixels_per_unit >= Precision::Single.max_pixels_per_unit() && self.supports(Precision::Double, params.kind) {
+            Precision::Double
+        } else {
+            Precision::Single
+        }
+    }
 
-    let finished = command_buffer.execute(queue.clone()).unwrap();
-    finished.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+    /// Records a dispatch that renders `params` into all of `image`, coloring with a palette
+    /// uploaded by `Palette::upload`.
+    pub fn dispatch(&self, builder: AutoCommandBufferBuilder, image: Arc<StorageImage<Format>>,
+                    palette: Arc<CpuAccessibleBuffer<[[f32; 4]]>>, params: &MandelbrotParams)
+        -> Result<AutoCommandBufferBuilder, Box<dyn Error>>
+    {
+        let pipeline = &self.pipelines[params.kind.index() as usize];
+        let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+                           .add_image(image.clone())?
+                           .add_buffer(palette)?
+                           .build()?
+                          );
+
+        Ok(builder.dispatch(group_count(&image), pipeline.clone(), set, params.push_constants())?)
+    }
 
-    let buffer_content = buf.read().unwrap();
-    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(1024, 1024, &buffer_content[..]).unwrap();
+    /// Like `dispatch`, but in double precision.
+    pub fn dispatch_double(&self, builder: AutoCommandBufferBuilder, image: Arc<StorageImage<Format>>,
+                           palette: Arc<CpuAccessibleBuffer<[[f32; 4]]>>, params: &MandelbrotParams)
+        -> Result<AutoCommandBufferBuilder, Box<dyn Error>>
+    {
+        if let FractalKind::Multibrot { power } = params.kind {
+            if !has_whole_power(params.kind) {
+                return Err(MandelbrotError::FractionalPower(power).into());
+            }
+        }
+        let pipeline = self.double_pipelines.get(params.kind.index() as usize)
+            .ok_or(MandelbrotError::Float64Unsupported)?;
+        let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+                           .add_image(image.clone())?
+                           .add_buffer(palette)?
+                           .build()?
+                          );
+
+        Ok(builder.dispatch(group_count(&image), pipeline.clone(), set, params.double_push_constants())?)
+    }
 
-    image.save("image.png").unwrap();
+    /// Renders around the center of `orbit`, uploaded by `ReferenceOrbit::upload`, by perturbation.
+    /// `params.center` is ignored.
+    pub fn dispatch_perturbed(&self, builder: AutoCommandBufferBuilder, image: Arc<StorageImage<Format>>,
+                              palette: Arc<CpuAccessibleBuffer<[[f32; 4]]>>,
+                              orbit: Arc<CpuAccessibleBuffer<[[f64; 2]]>>, params: &MandelbrotParams)
+        -> Result<AutoCommandBufferBuilder, Box<dyn Error>>
+    {
+        if params.kind != FractalKind::Mandelbrot {
+            return Err(MandelbrotError::PerturbationUnsupported(params.kind).into());
+        }
+        let pipeline = self.perturbation_pipeline.as_ref().ok_or(MandelbrotError::Float64Unsupported)?;
+        let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+                           .add_image(image.clone())?
+                           .add_buffer(palette)?
+                           .add_buffer(orbit)?
+                           .build()?
+                          );
+
+        Ok(builder.dispatch(group_count(&image), pipeline.clone(), set, params.perturbation_push_constants())?)
+    }
+}
 
+/// Whether the double precision shader, which can only multiply repeatedly, renders `kind` exactly.
+fn has_whole_power(kind: FractalKind) -> bool {
+    match kind {
+        FractalKind::Multibrot { power } => power.fract() == 0.0,
+        _ => true,
+    }
 }
 
+/// Enough work groups to cover all of `image`. Rounds up, the shaders skip the invocations outside
+/// the image.
+fn group_count(image: &StorageImage<Format>) -> [u32; 3] {
+    let dimensions = image.dimensions();
+    [(dimensions.width() + LOCAL_SIZE - 1) / LOCAL_SIZE, (dimensions.height() + LOCAL_SIZE - 1) / LOCAL_SIZE, 1]
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::device::Device;
+use vulkano::memory::DeviceMemoryAllocError;
+
+/// Built-in gradients, as lists of hex stops.
+const BUILTIN: &[(&str, &str)] = &[
+    ("classic", "004c00 4c0000 ff0000"),
+    ("grayscale", "ffffff 000000"),
+    ("fire", "000000 7f0000 ff4000 ffbf00 ffffff"),
+    ("ocean", "000428 004e92 00b4db e0ffff"),
+    ("ultra", "000764 206bcb edffff ffaa00 000200"),
+];
+
+#[derive(Debug)]
+pub enum PaletteError {
+    Io(io::Error),
+    /// A stop that isn't a 6 digit hex color, with its line number.
+    InvalidColor(usize, String),
+    Empty,
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaletteError::Io(err) => write!(f, "Failed to read palette: {}", err),
+            PaletteError::InvalidColor(line, text) => write!(f, "Invalid color {:?} on line {}, expected RRGGBB.", text, line),
+            PaletteError::Empty => write!(f, "Palette has no colors."),
+        }
+    }
+}
+
+impl Error for PaletteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PaletteError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PaletteError {
+    fn from(err: io::Error) -> PaletteError {
+        PaletteError::Io(err)
+    }
+}
+
+/// A gradient through evenly spaced color stops. It wraps around from the last stop back to the
+/// first, so it can be cycled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<[f32; 3]>,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::builtin("classic").expect("classic palette is built in")
+    }
+}
+
+impl Palette {
+    pub fn builtin_names() -> Vec<&'static str> {
+        BUILTIN.iter().map(|&(name, _)| name).collect()
+    }
+
+    pub fn builtin(name: &str) -> Option<Palette> {
+        BUILTIN.iter()
+            .find(|&&(builtin, _)| builtin.eq_ignore_ascii_case(name))
+            .map(|&(_, stops)| Palette::parse(stops).expect("built in palettes are valid"))
+    }
+
+    /// Hex stops like `ff8800` or `#ff8800`, separated by whitespace or commas. Everything after `//`
+    /// on a line is ignored.
+    pub fn parse(text: &str) -> Result<Palette, PaletteError> {
+        let mut colors = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or("");
+            for stop in line.split(|c: char| c.is_whitespace() || c == ',').filter(|s| !s.is_empty()) {
+                let color = parse_hex(stop).ok_or_else(|| PaletteError::InvalidColor(number + 1, stop.to_owned()))?;
+                colors.push(color);
+            }
+        }
+
+        if colors.is_empty() {
+            return Err(PaletteError::Empty);
+        }
+        Ok(Palette { colors: colors })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Palette, PaletteError> {
+        Palette::parse(&fs::read_to_string(path)?)
+    }
+
+    /// A built in palette if there is one called `name`, otherwise the palette file at that path.
+    pub fn from_name_or_file(name: &str) -> Result<Palette, PaletteError> {
+        match Palette::builtin(name) {
+            Some(palette) => Ok(palette),
+            None => Palette::from_file(name),
+        }
+    }
+
+    /// Uploads the stops for a shader to read as `vec4 colors[]` from a storage buffer.
+    pub fn upload(&self, device: &Arc<Device>) -> Result<Arc<CpuAccessibleBuffer<[[f32; 4]]>>, DeviceMemoryAllocError> {
+        CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::storage_buffer(),
+                                       self.colors.iter().map(|c| [c[0], c[1], c[2], 1.0]))
+    }
+}
+
+fn parse_hex(stop: &str) -> Option<[f32; 3]> {
+    let hex = stop.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([((value >> 16) & 0xff) as f32 / 255.0, ((value >> 8) & 0xff) as f32 / 255.0, (value & 0xff) as f32 / 255.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stops() {
+        let palette = Palette::parse("#ff0000, 00ff00\n0000ff").unwrap();
+        assert_eq!(palette.colors, vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+    }
+
+    #[test]
+    fn ignores_comments() {
+        let palette = Palette::parse("// a comment\nffffff // white\n// zzzzzz").unwrap();
+        assert_eq!(palette.colors, vec![[1.0, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn reports_invalid_stops_with_line() {
+        match Palette::parse("ffffff\n000000 12345g") {
+            Err(PaletteError::InvalidColor(line, text)) => {
+                assert_eq!(line, 2);
+                assert_eq!(text, "12345g");
+            }
+            other => panic!("expected InvalidColor, got {:?}", other),
+        }
+        assert!(Palette::parse("fff").is_err());
+    }
+
+    #[test]
+    fn empty() {
+        match Palette::parse("// nothing here\n\n") {
+            Err(PaletteError::Empty) => (),
+            other => panic!("expected Empty, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn builtins_parse() {
+        for name in Palette::builtin_names() {
+            assert!(Palette::builtin(name).is_some(), "{}", name);
+        }
+        assert!(Palette::builtin("CLASSIC").is_some());
+    }
+}
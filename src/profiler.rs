@@ -0,0 +1,187 @@
+use std::fmt;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use vulkano::OomError;
+use vulkano::command_buffer::CommandBuffer;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::instance::QueueFamily;
+use vulkano::sync::GpuFuture;
+
+use crate::timestamps::{TimestampPool, TimestampCommands, top_of_pipe, bottom_of_pipe};
+
+/// How long a named scope took on the GPU.
+#[derive(Debug, Clone)]
+pub struct ScopeTiming {
+    pub name: String,
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    /// More scopes were started than the profiler was created for.
+    OutOfQueries,
+    /// `end` was called for a scope that isn't open.
+    NotStarted(String),
+    CommandBufferFailed(OomError),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::OutOfQueries => write!(f, "Too many profiling scopes, call resolve() in between."),
+            ProfileError::NotStarted(name) => write!(f, "Profiling scope {:?} was never started.", name),
+            ProfileError::CommandBufferFailed(err) => write!(f, "Failed to record timestamps: {}", err),
+        }
+    }
+}
+
+impl Error for ProfileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProfileError::CommandBufferFailed(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<OomError> for ProfileError {
+    fn from(err: OomError) -> ProfileError {
+        ProfileError::CommandBufferFailed(err)
+    }
+}
+
+struct Scope {
+    name: String,
+    begin: u32,
+    end: Option<u32>,
+}
+
+/// Measures named regions of GPU work with timestamp queries.
+///
+/// `begin` and `end` return small command buffers to execute around the work, or `None` if the
+/// device can't write timestamps. Once the fence of that submission has been waited on, `resolve`
+/// returns the durations and makes the profiler ready for the next batch.
+pub struct Profiler {
+    timestamps: Option<TimestampPool>,
+    scopes: Vec<Scope>,
+    next_query: u32,
+}
+
+impl Profiler {
+    /// A profiler for queues of `family` with room for `max_scopes` scopes between two `resolve` calls.
+    pub fn new(device: &Arc<Device>, family: QueueFamily, max_scopes: u32) -> Profiler {
+        let timestamps = TimestampPool::new(device, family, max_scopes * 2);
+        if timestamps.is_none() {
+            log::info!("Timestamps are not supported on this device, GPU profiling is disabled.");
+        }
+
+        Profiler {
+            timestamps: timestamps,
+            scopes: Vec::new(),
+            next_query: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.timestamps.is_some()
+    }
+
+    /// Opens the scope `name`. Scopes may nest, but every scope has to be ended before `resolve`.
+    pub fn begin(&mut self, name: &str) -> Result<Option<TimestampCommands>, ProfileError> {
+        let timestamps = match self.timestamps {
+            Some(ref timestamps) => timestamps,
+            None => return Ok(None),
+        };
+        let index = self.next_query;
+        if index >= timestamps.len() {
+            return Err(ProfileError::OutOfQueries);
+        }
+
+        // The first scope of a batch resets the pool, so the previous results must be resolved already.
+        let commands = if index == 0 {
+            timestamps.reset_and_write(index)?
+        } else {
+            timestamps.write(index, top_of_pipe())?
+        };
+
+        self.next_query += 1;
+        self.scopes.push(Scope { name: name.to_owned(), begin: index, end: None });
+        Ok(Some(commands))
+    }
+
+    /// Closes the most recently opened scope called `name`.
+    pub fn end(&mut self, name: &str) -> Result<Option<TimestampCommands>, ProfileError> {
+        let timestamps = match self.timestamps {
+            Some(ref timestamps) => timestamps,
+            None => return Ok(None),
+        };
+        let index = self.next_query;
+        if index >= timestamps.len() {
+            return Err(ProfileError::OutOfQueries);
+        }
+
+        let scope = match self.scopes.iter_mut().rev().find(|s| s.name == name && s.end.is_none()) {
+            Some(scope) => scope,
+            None => return Err(ProfileError::NotStarted(name.to_owned())),
+        };
+
+        let commands = timestamps.write(index, bottom_of_pipe())?;
+        scope.end = Some(index);
+        self.next_query += 1;
+        Ok(Some(commands))
+    }
+
+    /// Executes `command_buffer` after `future`, wrapped in the scope `name`. If that fails, the
+    /// scope is forgotten again.
+    pub fn execute_scope<F, C>(&mut self, future: F, queue: &Arc<Queue>, name: &str, command_buffer: C)
+        -> Result<Box<dyn GpuFuture>, Box<dyn Error>>
+        where F: GpuFuture + 'static,
+              C: CommandBuffer + 'static
+    {
+        let (next_query, scopes) = (self.next_query, self.scopes.len());
+        let result = self.chain_scope(future, queue, name, command_buffer);
+        if result.is_err() {
+            self.next_query = next_query;
+            self.scopes.truncate(scopes);
+        }
+        result
+    }
+
+    fn chain_scope<F, C>(&mut self, future: F, queue: &Arc<Queue>, name: &str, command_buffer: C)
+        -> Result<Box<dyn GpuFuture>, Box<dyn Error>>
+        where F: GpuFuture + 'static,
+              C: CommandBuffer + 'static
+    {
+        let mut future = Box::new(future) as Box<dyn GpuFuture>;
+        if let Some(begin) = self.begin(name)? {
+            future = Box::new(future.then_execute(queue.clone(), begin)?);
+        }
+        future = Box::new(future.then_execute(queue.clone(), command_buffer)?);
+        if let Some(end) = self.end(name)? {
+            future = Box::new(future.then_execute(queue.clone(), end)?);
+        }
+        Ok(future)
+    }
+
+    /// The durations of every scope ended since the last call, in the order they were started. Only
+    /// call this after waiting on the fence of the submission that contains them.
+    pub fn resolve(&mut self) -> Vec<ScopeTiming> {
+        // Scopes whose timestamps were never submitted (e.g. begun but not ended) are left out.
+        let timestamps = self.timestamps.as_ref();
+        let used = self.scopes.iter().filter_map(|scope| scope.end).max().map_or(0, |end| end + 1);
+        let results = timestamps.map(|timestamps| timestamps.results(used)).unwrap_or_default();
+
+        let timings = self.scopes.drain(..)
+            .filter_map(|scope| {
+                let end = scope.end?;
+                let (begin, end) = ((*results.get(scope.begin as usize)?)?, (*results.get(end as usize)?)?);
+                Some(ScopeTiming { name: scope.name, duration: timestamps?.duration(begin, end) })
+            })
+            .collect();
+
+        self.next_query = 0;
+        timings
+    }
+}
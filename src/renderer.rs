@@ -0,0 +1,251 @@
+use std::sync::Arc;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::framebuffer::FramebufferAbstract;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::swapchain;
+use vulkano::swapchain::AcquireError;
+use vulkano::sync;
+use vulkano::sync::{GpuFuture, FlushError, FenceSignalFuture};
+use image::{ImageBuffer, Rgba};
+
+use crate::vulkan_init::VulkanInit;
+use crate::vulkan_window::{VulkanWindow, SwapchainStatus};
+use crate::error::InitError;
+use crate::timestamps::{TimestampPool, bottom_of_pipe};
+
+/// Number of frames the CPU may record ahead of the GPU by default.
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// What `Renderer::draw_frame` did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// The frame was submitted and queued for presentation.
+    Presented,
+    /// The window is minimized. Nothing was drawn, wait a bit before trying again.
+    Minimized,
+    /// The swapchain was out of date or couldn't be recreated yet. Nothing was drawn, the next
+    /// call retries.
+    Skipped,
+}
+
+/// Owns the synchronization for drawing to a `VulkanWindow`: acquiring an image, submitting the
+/// frame's commands and presenting, with up to `frames_in_flight` frames queued on the GPU.
+pub struct Renderer {
+    /// Color the render pass clears to before the draw callback runs.
+    pub clear_color: [f32; 4],
+    device: Arc<Device>,
+    graphics_queue: Arc<Queue>,
+    present_queue: Arc<Queue>,
+    // One fence per frame in flight, signalled once that frame is presented.
+    fences: Vec<Option<Arc<FenceSignalFuture<Box<dyn GpuFuture>>>>>,
+    // Two timestamps per frame in flight, around the frame's commands. `None` if unsupported.
+    timestamps: Option<Vec<TimestampPool>>,
+    frame: usize,
+    last_cpu_time: Option<Duration>,
+    last_gpu_time: Option<Duration>,
+    screenshot_path: Option<PathBuf>,
+}
+
+impl Renderer {
+    /// Fails with `SurfaceUnsupported` if the device was created without a present queue.
+    pub fn new(initializer: &VulkanInit, frames_in_flight: usize) -> Result<Renderer, InitError> {
+        let present_queue = initializer.present.clone().ok_or(InitError::SurfaceUnsupported(None))?;
+        let frames_in_flight = frames_in_flight.max(1);
+        let timestamps = (0..frames_in_flight)
+            .map(|_| TimestampPool::new(&initializer.device, initializer.graphics.family(), 2))
+            .collect::<Option<Vec<_>>>();
+
+        Ok(Renderer {
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            device: initializer.device.clone(),
+            graphics_queue: initializer.graphics.clone(),
+            present_queue: present_queue,
+            fences: (0..frames_in_flight).map(|_| None).collect(),
+            timestamps: timestamps,
+            frame: 0,
+            last_cpu_time: None,
+            last_gpu_time: None,
+            screenshot_path: None,
+        })
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// How long the last `draw_frame` call took on the CPU, not counting the wait for a free frame.
+    pub fn last_cpu_time(&self) -> Option<Duration> {
+        self.last_cpu_time
+    }
+
+    /// How long the GPU took for the most recently finished frame. `None` if the device has no
+    /// timestamp support.
+    pub fn last_gpu_time(&self) -> Option<Duration> {
+        self.last_gpu_time
+    }
+
+    /// Saves the next presented frame as a PNG at `path`. Needs `transfer_source` in
+    /// `WindowConfig::image_usage` and an 8 bit swapchain format.
+    pub fn request_screenshot<P: Into<PathBuf>>(&mut self, path: P) {
+        self.screenshot_path = Some(path.into());
+    }
+
+    /// Draws one frame into `window`. Recreates the swapchain first if that's pending, then waits for
+    /// the oldest frame in flight, acquires an image and begins the window's render pass. `draw`
+    /// records into the pass and gets the image's framebuffer; the pass is ended, submitted and
+    /// presented afterwards.
+    pub fn draw_frame<F>(&mut self, window: &mut VulkanWindow, draw: F) -> Result<FrameStatus, Box<dyn Error>>
+        where F: FnOnce(AutoCommandBufferBuilder, &Arc<FramebufferAbstract + Send + Sync>)
+                        -> Result<AutoCommandBufferBuilder, Box<dyn Error>>
+    {
+        if window.recreate_pending() {
+            match window.recreate_swapchain()? {
+                SwapchainStatus::Recreated => (),
+                SwapchainStatus::Minimized => return Ok(FrameStatus::Minimized),
+                SwapchainStatus::Retry => return Ok(FrameStatus::Skipped),
+            }
+        }
+
+        // Reuse this frame's slot only once the GPU is done with what was submitted from it.
+        if let Some(fence) = self.fences[self.frame].take() {
+            fence.wait(None)?;
+            if let Some(ref timestamps) = self.timestamps {
+                let pool = &timestamps[self.frame];
+                let results = pool.results(2);
+                if let (Some(&Some(start)), Some(&Some(end))) = (results.get(0), results.get(1)) {
+                    self.last_gpu_time = Some(pool.duration(start, end));
+                }
+            }
+        }
+        let cpu_start = Instant::now();
+
+        let (image_num, acquire_future) = match swapchain::acquire_next_image(window.swapchain.clone(), None) {
+            Ok(r) => r,
+            Err(AcquireError::OutOfDate) => {
+                window.request_recreate();
+                return Ok(FrameStatus::Skipped);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        // vulkano reports VK_SUBOPTIMAL_KHR as success. The size changes that cause it are caught by
+        // `VulkanWindow::poll_events` instead, which schedules the recreation.
+
+        let framebuffer = window.framebuffers[image_num].clone();
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.graphics_queue.family())?
+            .begin_render_pass(framebuffer.clone(), false, window.clear_values(self.clear_color))?;
+        let mut builder = draw(builder, &framebuffer)?
+            .end_render_pass()?;
+
+        let screenshot = match self.screenshot_path.take() {
+            Some(path) => if window.image_usage.transfer_source && is_rgba8(window.surface_format.format) {
+                let buffer = CpuAccessibleBuffer::from_iter(self.device.clone(), BufferUsage::transfer_destination(),
+                                                            (0 .. window.dimensions[0] * window.dimensions[1] * 4).map(|_| 0u8))?;
+                builder = builder.copy_image_to_buffer(window.images[image_num].clone(), buffer.clone())?;
+                Some((path, buffer))
+            } else {
+                log::warn!("Screenshots need `transfer_source` image usage and an 8 bit swapchain format.");
+                None
+            },
+            None => None,
+        };
+
+        let command_buffer = builder.build()?;
+
+        // Wait on the previous frame's submission as well, so frames are executed in order.
+        let previous = (self.frame + self.fences.len() - 1) % self.fences.len();
+        let previous_future = match self.fences[previous].clone() {
+            Some(fence) => Box::new(fence) as Box<dyn GpuFuture>,
+            None => Box::new(sync::now(self.device.clone())) as Box<dyn GpuFuture>,
+        };
+
+        let timestamps = self.timestamps.as_ref().map(|timestamps| &timestamps[self.frame]);
+        let mut future = Box::new(previous_future.join(acquire_future)) as Box<dyn GpuFuture>;
+        if let Some(timestamps) = timestamps {
+            future = Box::new(future.then_execute(self.graphics_queue.clone(), timestamps.reset_and_write(0)?)?);
+        }
+        future = Box::new(future.then_execute(self.graphics_queue.clone(), command_buffer)?);
+        if let Some(timestamps) = timestamps {
+            future = Box::new(future.then_execute(self.graphics_queue.clone(), timestamps.write(1, bottom_of_pipe())?)?);
+        }
+        let future = Box::new(future.then_swapchain_present(self.present_queue.clone(), window.swapchain.clone(),
+                                                            image_num)) as Box<dyn GpuFuture>;
+
+        let status = match future.then_signal_fence_and_flush() {
+            Ok(future) => {
+                let future = Arc::new(future);
+                // Stalls for one frame, which is fine for the odd screenshot.
+                if let Some((path, buffer)) = screenshot {
+                    future.wait(None)?;
+                    save_screenshot(&buffer.read()?, window.dimensions, window.surface_format.format, &path)?;
+                }
+                self.fences[self.frame] = Some(future);
+                FrameStatus::Presented
+            }
+            Err(FlushError::OutOfDate) => {
+                window.request_recreate();
+                FrameStatus::Skipped
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        self.last_cpu_time = Some(cpu_start.elapsed());
+        self.frame = (self.frame + 1) % self.fences.len();
+        Ok(status)
+    }
+
+    /// Blocks until every frame in flight has finished, e.g. before tearing down resources they use.
+    pub fn wait_idle(&mut self) -> Result<(), FlushError> {
+        for fence in &mut self.fences {
+            if let Some(fence) = fence.take() {
+                fence.wait(None)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A viewport covering a framebuffer of the given size, for pipelines built with a dynamic viewport.
+pub fn viewport_state(dimensions: [u32; 2]) -> DynamicState {
+    DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0 .. 1.0,
+        }]),
+        .. DynamicState::none()
+    }
+}
+
+fn is_rgba8(format: Format) -> bool {
+    match format {
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb | Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => true,
+        _ => false,
+    }
+}
+
+fn save_screenshot(data: &[u8], dimensions: [u32; 2], format: Format, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut pixels = data.to_vec();
+    // Swapchains are usually BGRA, the PNG wants RGBA.
+    match format {
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        },
+        _ => (),
+    }
+
+    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(dimensions[0], dimensions[1], pixels)
+        .ok_or("Screenshot buffer doesn't match the swapchain size.")?;
+    image.save(path)?;
+    log::info!("Saved screenshot to {}", path.display());
+    Ok(())
+}
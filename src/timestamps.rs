@@ -0,0 +1,201 @@
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+use vulkano::OomError;
+use vulkano::VulkanObject;
+use vulkano::buffer::BufferAccess;
+use vulkano::command_buffer::CommandBuffer;
+use vulkano::command_buffer::CommandBufferExecError;
+use vulkano::command_buffer::pool::CommandPool;
+use vulkano::command_buffer::pool::standard::StandardCommandPool;
+use vulkano::command_buffer::pool::standard::StandardCommandPoolAlloc;
+use vulkano::command_buffer::sys::{Flags, Kind, UnsafeCommandBuffer, UnsafeCommandBufferBuilder};
+use vulkano::device::Device;
+use vulkano::device::DeviceOwned;
+use vulkano::device::Queue;
+use vulkano::image::ImageAccess;
+use vulkano::image::ImageLayout;
+use vulkano::instance::QueueFamily;
+use vulkano::query::{QueryType, UnsafeQueryPool};
+use vulkano::sync::{AccessCheckError, AccessFlagBits, GpuFuture, PipelineStages};
+
+// VK_QUERY_RESULT_64_BIT and VK_QUERY_RESULT_WITH_AVAILABILITY_BIT. vulkano doesn't export the
+// flags, and its `copy_query_pool_results` always passes none, so results are read with the raw
+// function instead.
+const QUERY_RESULT_64_BIT: u32 = 0x1;
+const QUERY_RESULT_WITH_AVAILABILITY_BIT: u32 = 0x4;
+// VK_NOT_READY, returned when some of the queries aren't available.
+const NOT_READY: i32 = 1;
+
+/// A pool of GPU timestamp queries.
+///
+/// `AutoCommandBufferBuilder` can't write timestamps, so each operation is recorded into its own
+/// small command buffer which is executed before or after the work being measured.
+pub struct TimestampPool {
+    device: Arc<Device>,
+    command_pool: Arc<StandardCommandPool>,
+    queries: UnsafeQueryPool,
+    // Only the low timestamp_valid_bits of each tick value are meaningful.
+    valid_mask: u64,
+    // Nanoseconds per tick.
+    period: f32,
+}
+
+impl TimestampPool {
+    /// A pool of `count` timestamps for queues of `family`. `None` if the device can't write
+    /// timestamps on graphics and compute queues, or `family` has no valid timestamp bits.
+    pub fn new(device: &Arc<Device>, family: QueueFamily, count: u32) -> Option<TimestampPool> {
+        let limits = device.physical_device().limits();
+        if limits.timestamp_compute_and_graphics() == 0 || limits.timestamp_period() <= 0.0 {
+            return None;
+        }
+        let valid_bits = family.timestamp_valid_bits()?;
+        let valid_mask = if valid_bits >= 64 { u64::max_value() } else { (1 << valid_bits) - 1 };
+
+        let queries = match UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, count) {
+            Ok(queries) => queries,
+            Err(err) => {
+                log::warn!("Failed to create timestamp query pool: {}", err);
+                return None;
+            }
+        };
+
+        Some(TimestampPool {
+            device: device.clone(),
+            command_pool: Device::standard_command_pool(device, family),
+            queries: queries,
+            valid_mask: valid_mask,
+            period: limits.timestamp_period(),
+        })
+    }
+
+    pub fn len(&self) -> u32 {
+        self.queries.num_slots()
+    }
+
+    /// Resets every query. Has to run before any of them are written again.
+    pub fn reset(&self) -> Result<TimestampCommands, OomError> {
+        self.record(|builder| unsafe {
+            builder.reset_query_pool(self.queries.queries_range(0, self.len()).unwrap());
+        })
+    }
+
+    /// Writes timestamp `index` once all previously submitted commands have reached `stages`.
+    pub fn write(&self, index: u32, stages: PipelineStages) -> Result<TimestampCommands, OomError> {
+        self.record(|builder| unsafe {
+            builder.write_timestamp(self.queries.query(index).unwrap(), stages);
+        })
+    }
+
+    /// Resets every query, then writes timestamp `index` at the top of the pipe.
+    pub fn reset_and_write(&self, index: u32) -> Result<TimestampCommands, OomError> {
+        self.record(|builder| unsafe {
+            builder.reset_query_pool(self.queries.queries_range(0, self.len()).unwrap());
+            builder.write_timestamp(self.queries.query(index).unwrap(), top_of_pipe());
+        })
+    }
+
+    /// The tick values of the first `count` timestamps, `None` for the ones the GPU hasn't written
+    /// (yet). Doesn't wait, so wait on the fence of the submission that wrote them first. Empty if
+    /// reading them failed.
+    pub fn results(&self, count: u32) -> Vec<Option<u64>> {
+        let count = count.min(self.len());
+        if count == 0 {
+            return Vec::new();
+        }
+
+        // Each query gets its value followed by a non-zero word if it's available.
+        let mut results = vec![[0u64; 2]; count as usize];
+        let vk = self.device.pointers();
+        let result = unsafe {
+            vk.GetQueryPoolResults(self.device.internal_object(), self.queries.internal_object(), 0, count,
+                                   results.len() * mem::size_of::<[u64; 2]>(), results.as_mut_ptr() as *mut _,
+                                   mem::size_of::<[u64; 2]>() as u64,
+                                   QUERY_RESULT_64_BIT | QUERY_RESULT_WITH_AVAILABILITY_BIT)
+        };
+        if result != 0 && result as i32 != NOT_READY {
+            log::warn!("Failed to read timestamps: VkResult {}", result);
+            return Vec::new();
+        }
+
+        results.into_iter()
+            .map(|[ticks, available]| if available != 0 { Some(ticks & self.valid_mask) } else { None })
+            .collect()
+    }
+
+    /// The time between two tick values. The counter may wrap around in between.
+    pub fn duration(&self, start: u64, end: u64) -> Duration {
+        let ticks = end.wrapping_sub(start) & self.valid_mask;
+        let nanos = ticks as f64 * self.period as f64;
+        Duration::from_nanos(nanos as u64)
+    }
+
+    fn record<F>(&self, commands: F) -> Result<TimestampCommands, OomError>
+        where F: FnOnce(&mut UnsafeCommandBufferBuilder<StandardCommandPoolAlloc>)
+    {
+        let alloc = self.command_pool.alloc(false, 1)?.next().unwrap();
+        let mut builder = unsafe {
+            UnsafeCommandBufferBuilder::new(alloc.into_alloc(), Kind::primary(), Flags::OneTimeSubmit)?
+        };
+        commands(&mut builder);
+
+        Ok(TimestampCommands {
+            device: self.device.clone(),
+            inner: builder.build()?,
+        })
+    }
+}
+
+pub fn top_of_pipe() -> PipelineStages {
+    PipelineStages {
+        top_of_pipe: true,
+        .. PipelineStages::none()
+    }
+}
+
+pub fn bottom_of_pipe() -> PipelineStages {
+    PipelineStages {
+        bottom_of_pipe: true,
+        .. PipelineStages::none()
+    }
+}
+
+/// A command buffer recorded by `TimestampPool`, to be passed to `then_execute`.
+pub struct TimestampCommands {
+    device: Arc<Device>,
+    inner: UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+}
+
+unsafe impl DeviceOwned for TimestampCommands {
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+// The commands only touch the query pool, which is never used by anything else on the GPU, so there
+// is nothing to lock or check.
+unsafe impl CommandBuffer for TimestampCommands {
+    type PoolAlloc = StandardCommandPoolAlloc;
+
+    fn inner(&self) -> &UnsafeCommandBuffer<StandardCommandPoolAlloc> {
+        &self.inner
+    }
+
+    fn lock_submit(&self, _future: &GpuFuture, _queue: &Queue) -> Result<(), CommandBufferExecError> {
+        Ok(())
+    }
+
+    unsafe fn unlock(&self) {}
+
+    fn check_buffer_access(&self, _buffer: &BufferAccess, _exclusive: bool, _queue: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError>
+    {
+        Err(AccessCheckError::Unknown)
+    }
+
+    fn check_image_access(&self, _image: &ImageAccess, _layout: ImageLayout, _exclusive: bool, _queue: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError>
+    {
+        Err(AccessCheckError::Unknown)
+    }
+}
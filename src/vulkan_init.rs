@@ -1,52 +1,297 @@
 use std::sync::Arc;
 use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+use vulkano::instance::ApplicationInfo;
+use vulkano::instance::debug::DebugCallback;
 use vulkano::instance::PhysicalDevice;
 use vulkano::device::Device;
 use vulkano::device::Queue;
 use vulkano::device::DeviceExtensions;
 use vulkano::device::Features;
+use vulkano::instance::QueueFamily;
+use vulkano::swapchain::Surface;
+use winit::Window;
+
+use crate::device_selection::{DeviceSelection, DeviceRequirements, select_physical_device, union_features};
+use crate::error::InitError;
+use crate::debug;
+
+/// Whether the instance will be used to present to a window or only for offscreen/compute work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presentation {
+    Windowed,
+    Headless,
+}
+
+/// Everything needed to create an `Instance`. `windowed()` and `headless()` give sensible defaults,
+/// the fields can then be overridden with struct update syntax.
+pub struct InstanceConfig {
+    pub presentation: Presentation,
+    /// Extensions to enable on top of the ones required for presentation.
+    pub extensions: InstanceExtensions,
+    pub layers: Vec<String>,
+    pub app_info: Option<ApplicationInfo<'static>>,
+    /// Enable validation layers and debug messages when they are installed. Defaults to
+    /// `debug::validation_requested()`.
+    pub debug: bool,
+}
+
+impl InstanceConfig {
+    pub fn windowed() -> InstanceConfig {
+        InstanceConfig {
+            presentation: Presentation::Windowed,
+            extensions: InstanceExtensions::none(),
+            layers: Vec::new(),
+            app_info: Some(vulkano::app_info_from_cargo_toml!()),
+            debug: debug::validation_requested(),
+        }
+    }
+
+    /// No surface extensions are requested, so this works without a display server (e.g. lavapipe on CI).
+    pub fn headless() -> InstanceConfig {
+        InstanceConfig {
+            presentation: Presentation::Headless,
+            .. InstanceConfig::windowed()
+        }
+    }
+}
+
+pub fn create_instance(config: &InstanceConfig) -> Result<Arc<Instance>, InitError> {
+    let mut extensions = match config.presentation {
+        Presentation::Windowed => vulkano_win::required_extensions().union(&config.extensions),
+        Presentation::Headless => config.extensions,
+    };
+
+    let mut layers = config.layers.iter().map(|l| l.as_str()).collect::<Vec<_>>();
+
+    let validation_layer = if config.debug { debug::available_validation_layer() } else { None };
+    if let Some(ref layer) = validation_layer {
+        if !layers.contains(&layer.as_str()) {
+            layers.push(layer.as_str());
+        }
+    }
+    if config.debug {
+        extensions = extensions.union(&debug::debug_extensions());
+    }
+
+    Ok(Instance::new(config.app_info.as_ref(), &extensions, layers.iter())?)
+}
 
 pub struct VulkanInit<'a> {
     //instance: Arc<Instance>,
     pub physical: PhysicalDevice<'a>,
     pub device: Arc<Device>,
-    pub queue: Arc<Queue>
+    /// Only a compute queue when graphics wasn't required and the device has no graphics family.
+    pub graphics: Arc<Queue>,
+    /// A queue from a compute-only family when the device has one, otherwise shared with graphics.
+    pub compute: Arc<Queue>,
+    /// A queue from a transfer-only family when the device has one, otherwise shared with compute.
+    pub transfer: Arc<Queue>,
+    /// Only set when a surface was passed to `VulkanInitBuilder::present_surface`.
+    pub present: Option<Arc<Queue>>,
+    /// The required features plus whichever optional ones the device supports.
+    pub features: Features,
+    /// The required extensions plus whichever optional ones the device supports.
+    pub extensions: DeviceExtensions,
+    // Messages stop being logged once this is dropped.
+    _debug_callback: Option<DebugCallback>,
 }
 
 impl<'a> VulkanInit<'a> {
-    pub fn create(instance:&'a Arc<Instance>) -> VulkanInit<'a> {
+    /// Creates a device with the default selection policy and requirements.
+    pub fn create(instance: &'a Arc<Instance>) -> Result<VulkanInit<'a>, InitError> {
+        VulkanInit::builder(instance).build()
+    }
+
+    /// The highest sample count up to `requested` that the device supports for color attachments
+    /// (and depth attachments too, if `depth` is set). Always at least 1.
+    pub fn supported_sample_count(&self, requested: u32, depth: bool) -> u32 {
+        let limits = self.physical.limits();
+        let mut supported = limits.framebuffer_color_sample_counts();
+        if depth {
+            supported &= limits.framebuffer_depth_sample_counts();
+        }
+
+        [64, 32, 16, 8, 4, 2].iter().cloned()
+            .find(|&samples| samples <= requested && supported & samples != 0)
+            .unwrap_or(1)
+    }
+
+    pub fn builder(instance: &'a Arc<Instance>) -> VulkanInitBuilder<'a> {
+        let mut requirements = DeviceRequirements::default();
+        // Only ask for a swapchain if the instance can actually create surfaces.
+        requirements.extensions.khr_swapchain = instance.loaded_extensions().khr_surface;
+
+        VulkanInitBuilder {
+            instance: instance,
+            selection: DeviceSelection::default(),
+            requirements: requirements,
+            optional_features: Features::none(),
+            optional_extensions: DeviceExtensions::none(),
+            surface: None,
+        }
+    }
+}
+
+pub struct VulkanInitBuilder<'a> {
+    instance: &'a Arc<Instance>,
+    selection: DeviceSelection,
+    requirements: DeviceRequirements,
+    optional_features: Features,
+    optional_extensions: DeviceExtensions,
+    surface: Option<Arc<Surface<Arc<Window>>>>,
+}
+
+impl<'a> VulkanInitBuilder<'a> {
+    pub fn device_selection(mut self, selection: DeviceSelection) -> VulkanInitBuilder<'a> {
+        self.selection = selection;
+        self
+    }
+
+    pub fn requirements(mut self, requirements: DeviceRequirements) -> VulkanInitBuilder<'a> {
+        self.requirements = requirements;
+        self
+    }
+
+    /// Devices that don't support all of these are rejected.
+    pub fn required_features(mut self, features: Features) -> VulkanInitBuilder<'a> {
+        self.requirements.features = union_features(&self.requirements.features, &features);
+        self
+    }
+
+    /// Enabled when the chosen device supports them. Check `VulkanInit::features` for the result.
+    pub fn optional_features(mut self, features: Features) -> VulkanInitBuilder<'a> {
+        self.optional_features = union_features(&self.optional_features, &features);
+        self
+    }
+
+    /// Devices that don't support all of these are rejected.
+    pub fn required_extensions(mut self, extensions: DeviceExtensions) -> VulkanInitBuilder<'a> {
+        self.requirements.extensions = self.requirements.extensions.union(&extensions);
+        self
+    }
+
+    /// Enabled when the chosen device supports them. Check `VulkanInit::extensions` for the result.
+    pub fn optional_extensions(mut self, extensions: DeviceExtensions) -> VulkanInitBuilder<'a> {
+        self.optional_extensions = self.optional_extensions.union(&extensions);
+        self
+    }
+
+    /// Also create a queue that can present to `surface`. Devices that can't present to it are rejected.
+    pub fn present_surface(mut self, surface: &Arc<Surface<Arc<Window>>>) -> VulkanInitBuilder<'a> {
+        self.surface = Some(surface.clone());
+        self
+    }
+
+    pub fn build(self) -> Result<VulkanInit<'a>, InitError> {
+        // Registered first so that device creation is validated as well.
+        let debug_callback = debug::register_debug_callback(self.instance);
+
         // Step 2: Find physical device
         // The iterator has the same lifetime as the instance.
-        let physical = PhysicalDevice::enumerate(instance).next().expect("No physical devices available.");
+        let physical = select_physical_device(self.instance, &self.selection, &self.requirements, self.surface.as_ref())?;
+        log::info!("Using device {} (type: {:?})", physical.name(), physical.ty());
 
 
         // Step 3: Find queue families
         for family in physical.queue_families() {
-            println!("Found a queue family with {:?} queues", family.queues_count());
+            log::debug!("Found a queue family with {:?} queues", family.queues_count());
         }
 
-        let queue_family = physical.queue_families()
-            .find(|&q| q.supports_graphics())
-            .expect("Couldn't find a graphical queue family.");
-
-        // Step 4: Create Device and queue. 
-        let (device, mut queues) = {
-            let device_ext = DeviceExtensions  {
-                khr_swapchain: true,
-                .. DeviceExtensions::none()
-            };
-            Device::new(physical, &Features::none(), &device_ext, 
-                        [(queue_family, 0.5)].iter().cloned())
-                .expect("Failed to create device.")
+        let supports_present = |family: QueueFamily| -> Result<bool, InitError> {
+            match self.surface {
+                Some(ref surface) => Ok(surface.is_supported(family)?),
+                None => Ok(false),
+            }
         };
 
-        let queue = queues.next().unwrap();
+        // Prefer a graphics family that can also present, so we don't need to share the swapchain.
+        let mut graphics_family = None;
+        for family in physical.queue_families().filter(|q| q.supports_graphics()) {
+            if supports_present(family)? {
+                graphics_family = Some(family);
+                break;
+            }
+            graphics_family = graphics_family.or(Some(family));
+        }
+        let graphics_family = match graphics_family {
+            Some(family) => family,
+            // Selection lets compute-only devices through when graphics isn't required, and the
+            // compute family stands in for the graphics queue on those.
+            None if !self.requirements.graphics => physical.queue_families()
+                .find(|q| q.supports_compute())
+                .ok_or(InitError::NoGraphicsQueue)?,
+            None => return Err(InitError::NoGraphicsQueue),
+        };
+
+        let compute_family = physical.queue_families()
+            .find(|&q| q.supports_compute() && !q.supports_graphics())
+            .unwrap_or(graphics_family);
+
+        let transfer_family = physical.queue_families()
+            .find(|&q| q.supports_transfers() && !q.supports_graphics() && !q.supports_compute())
+            .unwrap_or(compute_family);
+
+        let present_family = match self.surface {
+            Some(_) if supports_present(graphics_family)? => Some(graphics_family),
+            Some(_) => {
+                let mut present_family = None;
+                for family in physical.queue_families() {
+                    if supports_present(family)? {
+                        present_family = Some(family);
+                        break;
+                    }
+                }
+                Some(present_family.ok_or(InitError::SurfaceUnsupported(None))?)
+            }
+            None => None,
+        };
+
+        // Each role gets its own queue while the family has some left, otherwise it shares the
+        // first queue already requested from that family.
+        let mut requests: Vec<(QueueFamily, f32)> = Vec::new();
+        let mut request_queue = |family: QueueFamily, priority: f32| -> usize {
+            let used = requests.iter().filter(|&&(f, _)| f.id() == family.id()).count();
+            if used < family.queues_count() {
+                requests.push((family, priority));
+                requests.len() - 1
+            } else {
+                requests.iter().position(|&(f, _)| f.id() == family.id()).unwrap()
+            }
+        };
 
-        VulkanInit { 
+        let graphics_slot = request_queue(graphics_family, 1.0);
+        let compute_slot = request_queue(compute_family, 0.5);
+        let transfer_slot = request_queue(transfer_family, 0.5);
+        // Presenting from the graphics queue is the common case, so don't take another queue for it.
+        let present_slot = present_family.map(|family| {
+            if family.id() == graphics_family.id() { graphics_slot } else { request_queue(family, 1.0) }
+        });
+
+        // Selection already made sure the required ones are supported.
+        let features = union_features(&self.requirements.features,
+                                      &self.optional_features.intersection(physical.supported_features()));
+        let extensions = self.requirements.extensions
+            .union(&self.optional_extensions.intersection(&DeviceExtensions::supported_by_device(physical)));
+
+        // Step 4: Create Device and queues. 
+        let (device, queues) = {
+            Device::new(physical, &features, &extensions, requests.iter().cloned())?
+        };
+
+        // The queues come back in the order they were requested.
+        let queues = queues.collect::<Vec<_>>();
+
+        Ok(VulkanInit { 
             physical: physical,
             device: device,
-            queue: queue
-        }
+            graphics: queues[graphics_slot].clone(),
+            compute: queues[compute_slot].clone(),
+            transfer: queues[transfer_slot].clone(),
+            present: present_slot.map(|slot| queues[slot].clone()),
+            features: features,
+            extensions: extensions,
+            _debug_callback: debug_callback,
+        })
     }
 }
-
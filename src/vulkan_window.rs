@@ -3,134 +3,688 @@ use vulkano::instance::Instance;
 use vulkano::framebuffer::Framebuffer;
 use vulkano::framebuffer::FramebufferAbstract;
 use vulkano::framebuffer::RenderPassAbstract;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::image::SwapchainImage;
+use vulkano::image::ImageUsage;
+use vulkano::image::AttachmentImage;
+use vulkano::image::ImageCreationError;
+use vulkano::format::ClearValue;
+use vulkano::command_buffer::DynamicState;
+use vulkano::swapchain::CompositeAlpha;
 use vulkano::swapchain::{SurfaceTransform, Swapchain, PresentMode};
+use vulkano::swapchain::Capabilities;
+use vulkano::swapchain::CapabilitiesError;
+use vulkano::swapchain::ColorSpace;
+use vulkano::format::Format;
 use vulkano::swapchain::SwapchainCreationError;
 use vulkano::swapchain::Surface;
-use vulkano_win::VkSurfaceBuild;
-use winit::EventsLoop;
-use winit::WindowBuilder;
+use vulkano::sync::SharingMode;
+use winit::{Event, EventsLoop, WindowEvent};
 use winit::Window;
-use winit::dpi::LogicalSize;
+use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize};
 
 use crate::vulkan_init::VulkanInit;
+use crate::error::InitError;
+use crate::renderer::viewport_state;
+use crate::window_config::{WindowConfig, WindowMode, SurfaceFormatPreference, DepthBuffer, find_monitor};
 
 pub struct VulkanWindow {
     pub events_loop: EventsLoop,
     pub dimensions: [u32; 2],
-    pub surface: Arc<Surface<Window>>,
-    pub swapchain: Arc<Swapchain<Window>>,
+    pub surface: Arc<Surface<Arc<Window>>>,
+    pub swapchain: Arc<Swapchain<Arc<Window>>>,
     pub render_pass: Arc<RenderPassAbstract + Send + Sync>,
-    pub framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>
-        //window: &'a Window,
+    pub framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    pub config: WindowConfig,
+    /// The mode picked from `config.present_modes` for the current swapchain.
+    pub present_mode: PresentMode,
+    /// The format picked according to `config.surface_format`. Recreated swapchains keep it, since the
+    /// render pass was built for it.
+    pub surface_format: SurfaceFormat,
+    pub composite_alpha: CompositeAlpha,
+    /// `config.image_usage` restricted to what the surface supports.
+    pub image_usage: ImageUsage,
+    pub images: Vec<Arc<SwapchainImage<Arc<Window>>>>,
+    /// Set if `config.depth_buffer` asked for one. The render pass then has a second attachment.
+    pub depth_format: Option<Format>,
+    /// The MSAA sample count actually used, `config.samples` lowered to what the device supports.
+    pub samples: u32,
+    /// Physical pixels per logical pixel on the monitor the window is currently on.
+    pub hidpi_factor: f64,
+    attachments: Attachments,
+    device: Arc<Device>,
+    graphics_queue: Arc<Queue>,
+    present_queue: Arc<Queue>,
+    recreate_pending: bool,
+    // Kept separately from the surface so a lost surface can be recreated for the same window.
+    window: Arc<Window>,
+}
+
+/// What `VulkanWindow::recreate_swapchain` managed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapchainStatus {
+    /// The swapchain, attachments and framebuffers were replaced.
+    Recreated,
+    /// The window has no area (e.g. it is minimized). Don't render until it's restored, recreation
+    /// stays pending.
+    Minimized,
+    /// The driver rejected the size (usually mid-resize) or the surface had to be recreated.
+    /// Recreation stays pending, try again next frame.
+    Retry,
+}
+
+/// The format and color space of the swapchain images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceFormat {
+    pub format: Format,
+    pub color_space: ColorSpace,
+}
+
+impl SurfaceFormat {
+    /// Whether writes are gamma encoded by the hardware. If not, and the color space is
+    /// `SrgbNonLinear`, fragment shaders have to apply the sRGB curve themselves.
+    pub fn is_srgb(&self) -> bool {
+        match self.format {
+            Format::B8G8R8A8Srgb | Format::R8G8B8A8Srgb | Format::A8B8G8R8SrgbPack32 => true,
+            _ => false,
+        }
+    }
 }
 
 impl VulkanWindow {
-    pub fn create(initializer: &VulkanInit, instance: Arc<Instance>) -> VulkanWindow {
+    /// The surface has to exist before the device, so `VulkanInit` can pick a queue that presents to it.
+    pub fn create_surface(instance: &Arc<Instance>, config: &WindowConfig)
+        -> Result<(EventsLoop, Arc<Surface<Arc<Window>>>), InitError>
+    {
         // Step 6: Create windows with event loop
         let events_loop = EventsLoop::new();
-        let surface = WindowBuilder::new()
-            .with_title("Vulkano Experiments")
-            .with_dimensions(LogicalSize::new(600.0, 600.0))
-            //.with_resizable(false)
-            .build_vk_surface(&events_loop, instance.clone()).unwrap();
-        //let window = surface.window();
-
-
-        let (swapchain, images) =        {
-            // Step 7: get the capabilities of the surface
-            let caps = surface.capabilities(initializer.physical).unwrap();
-
-            let dimensions = if let Some(dimensions) = surface.window().get_inner_size() {
-                // convert to physical pixels
-                let dimensions: (u32, u32) = dimensions.to_physical(surface.window().get_hidpi_factor()).into();
-                [dimensions.0, dimensions.1]
-            } else {
-                // The window no longer exists so exit the application.
-                panic!("Unable to get dimension");
-            };
-
-            let alpha = caps.supported_composite_alpha.iter().next().unwrap();
-            let format = caps.supported_formats[0].0;
-
-            // Step 8: Create a swapchain
-            Swapchain::new(initializer.device.clone(), surface.clone(),
-            caps.min_image_count, format, dimensions, 1, caps.supported_usage_flags, &initializer.queue, 
-            SurfaceTransform::Identity, alpha, PresentMode::Fifo, true, None).unwrap()
+        let window = config.window_builder(&events_loop).build(&events_loop)
+            .map_err(vulkano_win::CreationError::WindowCreationError)?;
+        let surface = vulkano_win::create_vk_surface(Arc::new(window), instance.clone())
+            .map_err(vulkano_win::CreationError::SurfaceCreationError)?;
+
+        // winit can only position a window once it exists.
+        match config.mode {
+            WindowMode::Windowed => if let Some(position) = config.position {
+                surface.window().set_position(position);
+            },
+            WindowMode::Borderless(monitor) => {
+                let monitor = find_monitor(&events_loop, monitor);
+                surface.window().set_position(monitor.get_position().to_logical(monitor.get_hidpi_factor()));
+            }
+            WindowMode::Fullscreen(_) => (),
+        }
+
+        Ok((events_loop, surface))
+    }
+
+    /// `config` should be the same one that was passed to `create_surface`.
+    pub fn create(initializer: &VulkanInit, events_loop: EventsLoop, surface: Arc<Surface<Arc<Window>>>,
+                  config: WindowConfig) -> Result<VulkanWindow, InitError>
+    {
+        let present_queue = initializer.present.clone().ok_or(InitError::SurfaceUnsupported(None))?;
+
+        let caps = surface.capabilities(initializer.physical)?;
+        // Nothing sensible to do with a minimized window here, so just start out at the smallest size.
+        let dimensions = surface_dimensions(surface.window(), &caps)?.unwrap_or(caps.min_image_extent);
+
+        let (swapchain, images, choices) = create_swapchain(&initializer.device, &surface,
+                                                            &initializer.graphics, &present_queue,
+                                                            &config, &caps, dimensions, None, None)?;
+
+        let depth_format = match config.depth_buffer {
+            DepthBuffer::None => None,
+            depth_buffer => Some(choose_depth_format(&initializer.device, depth_buffer)?),
         };
 
-        // Step 10: Setup render pass
-        let render_pass = Arc::new(vulkano::single_pass_renderpass!(initializer.device.clone(), 
-                                                                    attachments: {
-                                                                        color: {
-                                                                            load: Clear,
-                                                                            store: Store,
-                                                                            format: swapchain.format(),
-                                                                            samples: 1,
-                                                                        }
-                                                                    },
-                                                                    pass: {
-                                                                        color: [color],
-                                                                        depth_stencil: {}
-                                                                    }).unwrap());
+        let samples = initializer.supported_sample_count(config.samples, depth_format.is_some());
+        if samples != config.samples {
+            log::warn!("{}x MSAA is not supported, using {}x.", config.samples, samples);
+        }
 
+        // Step 10: Setup render pass
+        let render_pass = create_render_pass(&initializer.device, swapchain.format(), depth_format, samples)?;
 
+        let attachments = create_attachments(&initializer.device, images[0].dimensions(), swapchain.format(),
+                                             depth_format, samples)?;
 
         // Step 13: Create Frame buffers from dynamic state, render passes, and swapchain images
-        let framebuffers = images.iter().map(|image| {
-            Arc::new(
-                Framebuffer::start(render_pass.clone())
-                .add(image.clone()).unwrap()
-                .build().unwrap()) as Arc<FramebufferAbstract + Send + Sync>
-        }).collect::<Vec<_>>();
+        let framebuffers = create_framebuffers(&render_pass, &images, &attachments)?;
 
 
-        VulkanWindow {
+        Ok(VulkanWindow {
             events_loop: events_loop,
             dimensions: images[0].dimensions(),
-            surface: surface,
             swapchain: swapchain,
             render_pass: render_pass,
             framebuffers: framebuffers,
-            //window: window
-        }
+            config: config,
+            present_mode: choices.present_mode,
+            surface_format: choices.surface_format,
+            composite_alpha: choices.composite_alpha,
+            image_usage: choices.image_usage,
+            images: images,
+            depth_format: depth_format,
+            samples: samples,
+            hidpi_factor: surface.window().get_hidpi_factor(),
+            attachments: attachments,
+            device: initializer.device.clone(),
+            graphics_queue: initializer.graphics.clone(),
+            present_queue: present_queue,
+            recreate_pending: false,
+            window: surface.window().clone(),
+            surface: surface,
+        })
     }
 
-    pub fn recreate_swapchain(&mut self) {
-        let dimensions = if let Some(dimensions) = self.window().get_inner_size() {
-            // convert to physical pixels
-            let dimensions: (u32, u32) = dimensions.to_physical(self.window().get_hidpi_factor()).into();
-            [dimensions.0, dimensions.1]
-        } else {
-            // The window no longer exists so exit the application.
-            return;
+    /// Recreates the swapchain for the current window size and `config`. Returns `WindowClosed` if
+    /// the window is gone. Unless this returns `Recreated`, `recreate_pending` stays set.
+    pub fn recreate_swapchain(&mut self) -> Result<SwapchainStatus, InitError> {
+        self.recreate_pending = true;
+
+        let caps = match self.surface.capabilities(self.device.physical_device()) {
+            Ok(caps) => caps,
+            Err(CapabilitiesError::SurfaceLost) => {
+                self.recreate_surface()?;
+                self.surface.capabilities(self.device.physical_device())?
+            }
+            Err(err) => return Err(err.into()),
         };
 
+        let dimensions = match surface_dimensions(&self.window, &caps)? {
+            Some(dimensions) => dimensions,
+            None => return Ok(SwapchainStatus::Minimized),
+        };
+
+        // A swapchain can only be handed down to one for the same surface. The surface may have been
+        // replaced by this call or by an earlier one that returned `Retry`.
+        let old_swapchain = if Arc::ptr_eq(self.swapchain.surface(), &self.surface) {
+            Some(&self.swapchain)
+        } else {
+            None
+        };
 
         // Step 8: Create a swapchain
-        let (new_swapchain, new_images) = match self.swapchain.recreate_with_dimension(dimensions) {
+        let (new_swapchain, new_images, choices) = match create_swapchain(&self.device, &self.surface,
+                                                                          &self.graphics_queue, &self.present_queue,
+                                                                          &self.config, &caps, dimensions, old_swapchain,
+                                                                          Some(self.surface_format)) {
             Ok(r) => r, 
-            // The user is in the process of resizing or smth. Just keep going. What could possibly go wrong?!
-            Err(SwapchainCreationError::UnsupportedDimensions) => return,
-            Err(err) => panic!("{:?}", err),
+            // The size changed again between querying it and creating the swapchain.
+            Err(InitError::SwapchainFailed(SwapchainCreationError::UnsupportedDimensions)) => {
+                return Ok(SwapchainStatus::Retry);
+            }
+            Err(InitError::SwapchainFailed(SwapchainCreationError::SurfaceLost)) => {
+                self.recreate_surface()?;
+                return Ok(SwapchainStatus::Retry);
+            }
+            Err(err) => return Err(err),
         };
 
+        // The old swapchain is retired now and can't be handed down again, so keep the new one even
+        // if the rest fails. The recreation stays pending in that case.
         self.swapchain = new_swapchain;
+        self.present_mode = choices.present_mode;
+        self.surface_format = choices.surface_format;
+        self.composite_alpha = choices.composite_alpha;
+        self.image_usage = choices.image_usage;
 
+        // The depth and multisampled images have to match the new size.
+        self.attachments = create_attachments(&self.device, new_images[0].dimensions(), self.swapchain.format(),
+                                              self.depth_format, self.samples)?;
 
         // Step 13: Create Frame buffers from dynamic state, render passes, and swapchain images
-        self.framebuffers = new_images.iter().map(|image| {
-            Arc::new(
-                Framebuffer::start(self.render_pass.clone())
-                .add(image.clone()).unwrap()
-                .build().unwrap()) as Arc<FramebufferAbstract + Send + Sync>
-        }).collect::<Vec<_>>();
+        self.framebuffers = create_framebuffers(&self.render_pass, &new_images, &self.attachments)?;
 
         self.dimensions = new_images[0].dimensions();
+        self.images = new_images;
+        self.hidpi_factor = self.window.get_hidpi_factor();
+        // Only now, so that a failure above leaves the recreation pending.
+        self.recreate_pending = false;
+        Ok(SwapchainStatus::Recreated)
+    }
+
+    /// Replaces a lost surface with a new one for the same window.
+    fn recreate_surface(&mut self) -> Result<(), InitError> {
+        log::warn!("Surface lost, recreating it.");
+
+        let surface = vulkano_win::create_vk_surface(self.window.clone(), self.device.instance().clone())
+            .map_err(vulkano_win::CreationError::SurfaceCreationError)?;
+        if !surface.is_supported(self.present_queue.family())? {
+            return Err(InitError::SurfaceUnsupported(None));
+        }
+
+        self.surface = surface;
+        Ok(())
+    }
+
+    /// Clear values for `begin_render_pass`, clearing the color to `color` and depth to the far plane.
+    pub fn clear_values(&self, color: [f32; 4]) -> Vec<ClearValue> {
+        let mut clear_values = vec![color.into()];
+        // The swapchain image is only resolved into, so it doesn't need clearing.
+        if self.samples > 1 {
+            clear_values.push(ClearValue::None);
+        }
+        match self.config.depth_buffer {
+            DepthBuffer::None => (),
+            DepthBuffer::Depth => clear_values.push(ClearValue::Depth(1.0)),
+            DepthBuffer::DepthStencil => clear_values.push(ClearValue::DepthStencil((1.0, 0))),
+        }
+        clear_values
+    }
+
+    /// Polls the events loop like `EventsLoop::poll_events`, but also notices resizes and scale factor
+    /// changes (e.g. when moving to another monitor) and schedules a swapchain recreation for them.
+    pub fn poll_events<F: FnMut(Event)>(&mut self, mut callback: F) {
+        let mut resized = false;
+        let mut hidpi_factor = None;
+        self.events_loop.poll_events(|event| {
+            match event {
+                Event::WindowEvent { event: WindowEvent::Resized(_), .. } => resized = true,
+                Event::WindowEvent { event: WindowEvent::HiDpiFactorChanged(factor), .. } => hidpi_factor = Some(factor),
+                _ => (),
+            }
+            callback(event);
+        });
+
+        if let Some(factor) = hidpi_factor {
+            log::info!("Scale factor changed from {} to {}.", self.hidpi_factor, factor);
+            self.hidpi_factor = factor;
+        }
+        if resized || hidpi_factor.is_some() {
+            self.request_recreate();
+        }
+    }
+
+    /// The size of the swapchain images.
+    pub fn physical_size(&self) -> PhysicalSize {
+        PhysicalSize::new(self.dimensions[0] as f64, self.dimensions[1] as f64)
+    }
+
+    /// The size of the swapchain images in logical pixels, the units winit reports window sizes and
+    /// cursor positions in.
+    pub fn logical_size(&self) -> LogicalSize {
+        self.physical_size().to_logical(self.hidpi_factor)
+    }
+
+    /// Converts a position from a window event (e.g. the cursor) into swapchain pixels.
+    pub fn to_physical(&self, position: LogicalPosition) -> PhysicalPosition {
+        position.to_physical(self.hidpi_factor)
+    }
+
+    /// A viewport covering the whole swapchain image, for pipelines built with a dynamic viewport.
+    pub fn dynamic_state(&self) -> DynamicState {
+        viewport_state(self.dimensions)
+    }
+
+    /// Whether the window's size no longer matches the swapchain, so it should be recreated even
+    /// though presenting still works. Queries the surface, so don't call it every frame,
+    /// `poll_events` already notices resizes.
+    pub fn is_suboptimal(&self) -> bool {
+        let caps = match self.surface.capabilities(self.device.physical_device()) {
+            Ok(caps) => caps,
+            Err(_) => return false,
+        };
+        match surface_dimensions(&self.window, &caps) {
+            Ok(Some(dimensions)) => dimensions != self.dimensions,
+            _ => false,
+        }
+    }
+
+    /// Makes the next `recreate_pending` call return true, e.g. after the window was resized.
+    pub fn request_recreate(&mut self) {
+        self.recreate_pending = true;
+    }
+
+    pub fn recreate_pending(&self) -> bool {
+        self.recreate_pending
+    }
+
+    /// Replaces the present mode preference list. Takes effect once the swapchain is recreated.
+    pub fn set_present_modes(&mut self, present_modes: Vec<PresentMode>) {
+        self.config.present_modes = present_modes;
+        self.request_recreate();
+    }
+
+    /// Switches the existing window to `mode` and schedules a swapchain recreation for the new size.
+    pub fn set_mode(&mut self, mode: WindowMode) {
+        match mode {
+            WindowMode::Windowed => {
+                self.window.set_fullscreen(None);
+                self.window.set_decorations(self.config.decorations);
+                self.window.set_inner_size(self.config.size);
+            }
+            WindowMode::Fullscreen(monitor) => {
+                self.window.set_fullscreen(Some(find_monitor(&self.events_loop, monitor)));
+            }
+            WindowMode::Borderless(monitor) => {
+                let monitor = find_monitor(&self.events_loop, monitor);
+                let hidpi_factor = monitor.get_hidpi_factor();
+                self.window.set_fullscreen(None);
+                self.window.set_decorations(false);
+                self.window.set_position(monitor.get_position().to_logical(hidpi_factor));
+                self.window.set_inner_size(monitor.get_dimensions().to_logical(hidpi_factor));
+            }
+        }
+        self.config.mode = mode;
+        self.request_recreate();
+    }
+
+    /// Goes fullscreen on the monitor the window is on, or back to a normal window.
+    pub fn toggle_fullscreen(&mut self) {
+        let mode = match self.config.mode {
+            WindowMode::Windowed => {
+                let current = self.window.get_current_monitor().get_name();
+                let index = self.events_loop.get_available_monitors().position(|m| m.get_name() == current);
+                WindowMode::Fullscreen(index)
+            }
+            WindowMode::Fullscreen(_) | WindowMode::Borderless(_) => WindowMode::Windowed,
+        };
+        self.set_mode(mode);
     }
 
     #[inline(always)]
     pub fn window(&self) -> &Window {
-        self.surface.window()
+        &self.window
+    }
+}
+
+/// The first mode in `preferred` that the surface supports. Fifo is always supported, so it's the fallback.
+pub fn choose_present_mode(caps: &Capabilities, preferred: &[PresentMode]) -> PresentMode {
+    preferred.iter().cloned()
+        .find(|&mode| caps.present_modes.supports(mode))
+        .unwrap_or(PresentMode::Fifo)
+}
+
+/// Picks a format matching `preference`, then any sRGB one, then the first one listed. Returns `None`
+/// only if the surface has no formats at all.
+pub fn choose_surface_format(caps: &Capabilities, preference: SurfaceFormatPreference) -> Option<SurfaceFormat> {
+    let candidates: &[(Format, ColorSpace)] = match preference {
+        SurfaceFormatPreference::Srgb => &[(Format::B8G8R8A8Srgb, ColorSpace::SrgbNonLinear),
+                                           (Format::R8G8B8A8Srgb, ColorSpace::SrgbNonLinear)],
+        SurfaceFormatPreference::Unorm => &[(Format::B8G8R8A8Unorm, ColorSpace::SrgbNonLinear),
+                                            (Format::R8G8B8A8Unorm, ColorSpace::SrgbNonLinear)],
+        SurfaceFormatPreference::Hdr10 => &[(Format::A2B10G10R10UnormPack32, ColorSpace::Hdr10St2084),
+                                            (Format::A2R10G10B10UnormPack32, ColorSpace::Hdr10St2084)],
+        SurfaceFormatPreference::ScRgb => &[(Format::R16G16B16A16Sfloat, ColorSpace::ExtendedSrgbLinear)],
+    };
+
+    let to_surface_format = |&(format, color_space): &(Format, ColorSpace)| SurfaceFormat { format: format, color_space: color_space };
+
+    candidates.iter()
+        .find(|candidate| caps.supported_formats.contains(candidate))
+        .or_else(|| caps.supported_formats.iter().find(|&&(format, color_space)| {
+            color_space == ColorSpace::SrgbNonLinear && SurfaceFormat { format: format, color_space: color_space }.is_srgb()
+        }))
+        .or_else(|| caps.supported_formats.get(0))
+        .map(to_surface_format)
+}
+
+/// `config.image_count` clamped to what the surface allows.
+pub fn choose_image_count(caps: &Capabilities, desired: u32) -> u32 {
+    let max = caps.max_image_count.unwrap_or(u32::max_value());
+    desired.max(caps.min_image_count).min(max)
+}
+
+/// The first mode in `preferred` that the surface supports, otherwise whatever it supports first.
+pub fn choose_composite_alpha(caps: &Capabilities, preferred: &[CompositeAlpha]) -> Option<CompositeAlpha> {
+    preferred.iter().cloned()
+        .find(|&alpha| caps.supported_composite_alpha.supports(alpha))
+        .or_else(|| caps.supported_composite_alpha.iter().next())
+}
+
+/// The usage flags in `requested` that `supported` also has.
+fn restrict_usage(requested: ImageUsage, supported: ImageUsage) -> ImageUsage {
+    ImageUsage {
+        transfer_source: requested.transfer_source && supported.transfer_source,
+        transfer_destination: requested.transfer_destination && supported.transfer_destination,
+        sampled: requested.sampled && supported.sampled,
+        storage: requested.storage && supported.storage,
+        color_attachment: requested.color_attachment && supported.color_attachment,
+        depth_stencil_attachment: requested.depth_stencil_attachment && supported.depth_stencil_attachment,
+        transient_attachment: requested.transient_attachment && supported.transient_attachment,
+        input_attachment: requested.input_attachment && supported.input_attachment,
+    }
+}
+
+/// Everything `create_swapchain` had to pick based on the surface capabilities.
+struct SwapchainChoices {
+    present_mode: PresentMode,
+    surface_format: SurfaceFormat,
+    composite_alpha: CompositeAlpha,
+    image_usage: ImageUsage,
+}
+
+/// The window's size in physical pixels, clamped to the extents the surface allows. `None` if the
+/// window has no area, e.g. because it is minimized.
+fn surface_dimensions(window: &Window, caps: &Capabilities) -> Result<Option<[u32; 2]>, InitError> {
+    let dimensions = if let Some(dimensions) = window.get_inner_size() {
+        // convert to physical pixels
+        let dimensions: (u32, u32) = dimensions.to_physical(window.get_hidpi_factor()).into();
+        [dimensions.0, dimensions.1]
+    } else {
+        return Err(InitError::WindowClosed);
+    };
+
+    // Some platforms report a zero maximum extent while minimized.
+    if dimensions[0] == 0 || dimensions[1] == 0 || caps.max_image_extent[0] == 0 || caps.max_image_extent[1] == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some([
+        dimensions[0].max(caps.min_image_extent[0]).min(caps.max_image_extent[0]),
+        dimensions[1].max(caps.min_image_extent[1]).min(caps.max_image_extent[1]),
+    ]))
+}
+
+fn create_swapchain(device: &Arc<Device>, surface: &Arc<Surface<Arc<Window>>>, graphics_queue: &Arc<Queue>,
+                    present_queue: &Arc<Queue>, config: &WindowConfig, caps: &Capabilities, dimensions: [u32; 2],
+                    old_swapchain: Option<&Arc<Swapchain<Arc<Window>>>>, format: Option<SurfaceFormat>)
+    -> Result<(Arc<Swapchain<Arc<Window>>>, Vec<Arc<SwapchainImage<Arc<Window>>>>, SwapchainChoices), InitError>
+{
+    let alpha = choose_composite_alpha(caps, &config.composite_alpha)
+        .ok_or(InitError::SurfaceUnsupported(None))?;
+    let image_count = choose_image_count(caps, config.image_count);
+
+    let usage = restrict_usage(config.image_usage, caps.supported_usage_flags);
+    if usage != config.image_usage {
+        log::warn!("Swapchain image usage {:?} is not fully supported, using {:?}.", config.image_usage, usage);
+    }
+    if !usage.color_attachment {
+        return Err(InitError::SurfaceUnsupported(None));
     }
+    // The render pass and everything built for it depend on the format, so a recreated swapchain
+    // has to keep it, even if the surface was lost and replaced in between.
+    let surface_format = match format {
+        Some(format) if caps.supported_formats.contains(&(format.format, format.color_space)) => format,
+        Some(format) => {
+            log::error!("The surface no longer supports the swapchain's format {:?}.", format);
+            return Err(InitError::SurfaceUnsupported(None));
+        }
+        None => choose_surface_format(caps, config.surface_format).ok_or(InitError::SurfaceUnsupported(None))?,
+    };
+    let present_mode = choose_present_mode(caps, &config.present_modes);
+
+    // The images are drawn on the graphics queue, so they have to be shared if presenting happens elsewhere.
+    let sharing = if present_queue.family().id() == graphics_queue.family().id() {
+        SharingMode::from(graphics_queue)
+    } else {
+        SharingMode::from(&[graphics_queue, present_queue][..])
+    };
+
+    let (swapchain, images) = Swapchain::new(device.clone(), surface.clone(),
+        image_count, surface_format.format, dimensions, 1, usage, sharing, 
+        SurfaceTransform::Identity, alpha, present_mode, true, old_swapchain)?;
+
+    Ok((swapchain, images, SwapchainChoices {
+        present_mode: present_mode,
+        surface_format: surface_format,
+        composite_alpha: alpha,
+        image_usage: usage,
+    }))
 }
 
+/// The first of the candidate formats for `depth_buffer` that the device can use as an attachment.
+pub fn choose_depth_format(device: &Arc<Device>, depth_buffer: DepthBuffer) -> Result<Format, InitError> {
+    let candidates: &[Format] = match depth_buffer {
+        DepthBuffer::None => &[],
+        DepthBuffer::Depth => &[Format::D32Sfloat, Format::D24Unorm_S8Uint, Format::D16Unorm],
+        DepthBuffer::DepthStencil => &[Format::D32Sfloat_S8Uint, Format::D24Unorm_S8Uint, Format::D16Unorm_S8Uint],
+    };
+
+    // There's no way to query format support here, so just try to create a tiny attachment with each.
+    let mut last_err = None;
+    for &format in candidates {
+        match AttachmentImage::transient(device.clone(), [1, 1], format) {
+            Ok(_) => return Ok(format),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    Err(InitError::ImageFailed(last_err.unwrap_or(ImageCreationError::FormatNotSupported)))
+}
+
+/// The images the framebuffers need besides the swapchain image. Recreated with the swapchain.
+struct Attachments {
+    /// Rendered into instead of the swapchain image when multisampling, then resolved.
+    multisampled: Option<Arc<AttachmentImage>>,
+    depth: Option<Arc<AttachmentImage>>,
+}
+
+fn create_attachments(device: &Arc<Device>, dimensions: [u32; 2], color_format: Format,
+                      depth_format: Option<Format>, samples: u32) -> Result<Attachments, InitError>
+{
+    let multisampled = if samples > 1 {
+        Some(AttachmentImage::transient_multisampled(device.clone(), dimensions, samples, color_format)?)
+    } else {
+        None
+    };
+
+    let depth = match depth_format {
+        Some(format) if samples > 1 => Some(AttachmentImage::transient_multisampled(device.clone(), dimensions, samples, format)?),
+        Some(format) => Some(AttachmentImage::transient(device.clone(), dimensions, format)?),
+        None => None,
+    };
+
+    Ok(Attachments { multisampled: multisampled, depth: depth })
+}
+
+/// Attachments are ordered `[multisampled color], color, [depth]`, matching `clear_values`.
+fn create_render_pass(device: &Arc<Device>, color_format: Format, depth_format: Option<Format>, samples: u32)
+    -> Result<Arc<RenderPassAbstract + Send + Sync>, InitError>
+{
+    let render_pass = match (depth_format, samples > 1) {
+        (Some(depth_format), true) => Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                intermediary: {
+                    load: Clear,
+                    store: DontCare,
+                    format: color_format,
+                    samples: samples,
+                },
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: samples,
+                }
+            },
+            pass: {
+                color: [intermediary],
+                depth_stencil: {depth}
+                resolve: [color],
+            })?) as Arc<RenderPassAbstract + Send + Sync>,
+        (None, true) => Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                intermediary: {
+                    load: Clear,
+                    store: DontCare,
+                    format: color_format,
+                    samples: samples,
+                },
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [intermediary],
+                depth_stencil: {}
+                resolve: [color],
+            })?) as Arc<RenderPassAbstract + Send + Sync>,
+        (Some(depth_format), false) => Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth}
+            })?) as Arc<RenderPassAbstract + Send + Sync>,
+        (None, false) => Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            })?) as Arc<RenderPassAbstract + Send + Sync>,
+    };
+
+    Ok(render_pass)
+}
+
+fn create_framebuffers(render_pass: &Arc<RenderPassAbstract + Send + Sync>, images: &[Arc<SwapchainImage<Arc<Window>>>],
+                       attachments: &Attachments)
+    -> Result<Vec<Arc<FramebufferAbstract + Send + Sync>>, InitError>
+{
+    images.iter().map(|image| {
+        let framebuffer = match (&attachments.multisampled, &attachments.depth) {
+            (Some(multisampled), Some(depth)) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                .add(multisampled.clone())?
+                .add(image.clone())?
+                .add(depth.clone())?
+                .build()?) as Arc<FramebufferAbstract + Send + Sync>,
+            (Some(multisampled), None) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                .add(multisampled.clone())?
+                .add(image.clone())?
+                .build()?) as Arc<FramebufferAbstract + Send + Sync>,
+            (None, Some(depth)) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                .add(image.clone())?
+                .add(depth.clone())?
+                .build()?) as Arc<FramebufferAbstract + Send + Sync>,
+            (None, None) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                .add(image.clone())?
+                .build()?) as Arc<FramebufferAbstract + Send + Sync>,
+        };
+        Ok(framebuffer)
+    }).collect()
+}
@@ -0,0 +1,132 @@
+use vulkano::image::ImageUsage;
+use vulkano::swapchain::CompositeAlpha;
+use vulkano::swapchain::PresentMode;
+use winit::EventsLoop;
+use winit::MonitorId;
+use winit::WindowBuilder;
+use winit::dpi::{LogicalPosition, LogicalSize};
+
+/// Whether the window is a normal window or covers a whole monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    /// Fullscreen on the monitor with this index in `EventsLoop::get_available_monitors`, or on
+    /// the primary monitor if `None`.
+    Fullscreen(Option<usize>),
+    /// An undecorated window covering the chosen monitor, without taking exclusive control of it.
+    Borderless(Option<usize>),
+}
+
+/// Which kind of swapchain format to look for. If the surface doesn't offer it, an sRGB format is
+/// used, and failing that whatever the driver lists first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceFormatPreference {
+    /// 8 bit sRGB, so the hardware encodes gamma when writing.
+    Srgb,
+    /// 8 bit UNORM with the sRGB color space. Shaders have to encode gamma themselves.
+    Unorm,
+    /// 10 bit with the HDR10 (ST2084) color space. Needs `ext_swapchain_colorspace` on the instance.
+    Hdr10,
+    /// 16 bit float with the extended linear sRGB color space. Needs `ext_swapchain_colorspace` too.
+    ScRgb,
+}
+
+/// Whether the window's render pass gets a depth attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthBuffer {
+    None,
+    /// D32, D24S8 or D16, whichever is supported first.
+    Depth,
+    /// D32S8, D24S8 or D16S8, whichever is supported first.
+    DepthStencil,
+}
+
+/// How the window is created by `VulkanWindow::create_surface`.
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    pub title: String,
+    pub size: LogicalSize,
+    pub resizable: bool,
+    pub mode: WindowMode,
+    pub decorations: bool,
+    pub min_size: Option<LogicalSize>,
+    pub max_size: Option<LogicalSize>,
+    /// Where to put the window. Left to the window manager if `None`, ignored unless `Windowed`.
+    pub position: Option<LogicalPosition>,
+    /// Tried in order, falling back to `Fifo` (vsync) which every driver supports. Use `Mailbox` or
+    /// `Immediate` first for uncapped frame rates.
+    pub present_modes: Vec<PresentMode>,
+    pub surface_format: SurfaceFormatPreference,
+    /// Number of swapchain images, clamped to what the surface supports. 3 for triple buffering.
+    pub image_count: u32,
+    /// Tried in order, falling back to the first mode the surface supports. Put `PreMultiplied`
+    /// first (together with `transparent`) for a window the desktop shows through.
+    pub composite_alpha: Vec<CompositeAlpha>,
+    /// Ask the window system for a transparent window.
+    pub transparent: bool,
+    /// Usage of the swapchain images. Add `transfer_source` to copy them out, e.g. for screenshots.
+    pub image_usage: ImageUsage,
+    pub depth_buffer: DepthBuffer,
+    /// MSAA sample count. Lowered to what the device supports; 1 disables multisampling.
+    pub samples: u32,
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            title: "Vulkano Experiments".to_owned(),
+            size: LogicalSize::new(600.0, 600.0),
+            resizable: true,
+            mode: WindowMode::Windowed,
+            decorations: true,
+            min_size: None,
+            max_size: None,
+            position: None,
+            present_modes: vec![PresentMode::Fifo],
+            surface_format: SurfaceFormatPreference::Srgb,
+            image_count: 3,
+            composite_alpha: vec![CompositeAlpha::Opaque],
+            transparent: false,
+            image_usage: ImageUsage {
+                color_attachment: true,
+                .. ImageUsage::none()
+            },
+            depth_buffer: DepthBuffer::None,
+            samples: 1,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub(crate) fn window_builder(&self, events_loop: &EventsLoop) -> WindowBuilder {
+        let mut builder = WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_dimensions(self.size)
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_transparency(self.transparent);
+
+        if let Some(min_size) = self.min_size {
+            builder = builder.with_min_dimensions(min_size);
+        }
+        if let Some(max_size) = self.max_size {
+            builder = builder.with_max_dimensions(max_size);
+        }
+
+        match self.mode {
+            WindowMode::Windowed => builder,
+            WindowMode::Fullscreen(monitor) => builder.with_fullscreen(Some(find_monitor(events_loop, monitor))),
+            WindowMode::Borderless(monitor) => {
+                let monitor = find_monitor(events_loop, monitor);
+                let size = monitor.get_dimensions().to_logical(monitor.get_hidpi_factor());
+                builder.with_decorations(false).with_dimensions(size)
+            }
+        }
+    }
+}
+
+/// The monitor with the given index, falling back to the primary monitor if there is no such monitor.
+pub fn find_monitor(events_loop: &EventsLoop, index: Option<usize>) -> MonitorId {
+    index.and_then(|index| events_loop.get_available_monitors().nth(index))
+        .unwrap_or_else(|| events_loop.get_primary_monitor())
+}